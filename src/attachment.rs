@@ -0,0 +1,279 @@
+//! Images and files attached to messages
+
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// Maximum size of local file that is attached, in bytes
+pub const MAX_SIZE: u64 = 20 * 1024 * 1024;
+
+/// Kinds of attachments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentKind {
+    Image,
+    File,
+}
+
+/// Whether attachments referenced in input may read local files, e.g. `>>> file <path>` in
+/// transcripts. Input that isn't trusted could otherwise send any readable file to the provider
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum LocalFiles {
+    /// Only attachments by URL are allowed
+    #[default]
+    Deny,
+    /// Local paths are allowed, with relative paths resolved against directory, e.g. of the
+    /// input file
+    Allow(PathBuf),
+}
+
+/// Image or file attached to message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    #[serde(rename = "type")]
+    pub kind: AttachmentKind,
+    /// Path or URL that attachment is loaded from
+    pub source: String,
+    /// URL of image, or data URL with base64-encoded contents of local file. Empty until loaded
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+}
+
+impl Attachment {
+    /// Image at local path or URL
+    pub fn image(source: &str) -> Result<Self, Error> {
+        Self::new(AttachmentKind::Image, source).load()
+    }
+
+    /// File at local path
+    pub fn file(source: &str) -> Result<Self, Error> {
+        Self::new(AttachmentKind::File, source).load()
+    }
+
+    /// Attachment referencing `source`, without loading it
+    pub fn new(kind: AttachmentKind, source: &str) -> Self {
+        Self {
+            kind,
+            source: source.to_string(),
+            url: String::new(),
+        }
+    }
+
+    /// Attachment with `url` loaded from `source`, if not loaded yet. Local paths are relative to
+    /// current directory
+    pub fn load(self) -> Result<Self, Error> {
+        self.load_with(&LocalFiles::Allow(PathBuf::new()))
+    }
+
+    /// Attachment with `url` loaded from `source` if not loaded yet, reading local paths per `local`
+    pub fn load_with(self, local: &LocalFiles) -> Result<Self, Error> {
+        if !self.url.is_empty() {
+            return Ok(self);
+        }
+
+        let url = match (self.kind, is_url(&self.source)) {
+            (AttachmentKind::Image, true) => self.source.clone(),
+            (AttachmentKind::File, true) => {
+                return Err(Error::InvalidAttachment(format!(
+                    "{} - files must be local paths",
+                    self.source
+                )));
+            }
+            (kind, false) => match local {
+                LocalFiles::Allow(dir) => data_url(kind, &dir.join(&self.source))?,
+                LocalFiles::Deny => {
+                    return Err(Error::InvalidAttachment(format!(
+                        "{} - local files are only attached from input allowed by --allow-local-attachments",
+                        self.source
+                    )));
+                }
+            },
+        };
+
+        Ok(Self { url, ..self })
+    }
+
+    /// File name of `source`
+    pub fn filename(&self) -> &str {
+        Path::new(&self.source)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&self.source)
+    }
+
+    /// Media type and base64-encoded data, if `url` is a data URL
+    pub fn data(&self) -> Option<(&str, &str)> {
+        self.url.strip_prefix("data:")?.split_once(";base64,")
+    }
+}
+
+/// Whether `source` is a URL rather than a local path
+fn is_url(source: &str) -> bool {
+    ["http://", "https://", "data:"]
+        .iter()
+        .any(|scheme| source.starts_with(scheme))
+}
+
+/// Data URL with base64-encoded contents of file at `path`
+fn data_url(kind: AttachmentKind, path: &Path) -> Result<String, Error> {
+    let media_type = media_type(kind, path)?;
+
+    let size = std::fs::metadata(path)
+        .map_err(|e| Error::InvalidAttachment(format!("{} - {e}", path.display())))?
+        .len();
+    if size > MAX_SIZE {
+        return Err(Error::InvalidAttachment(format!(
+            "{} is {size} bytes, exceeding limit of {MAX_SIZE} bytes",
+            path.display()
+        )));
+    }
+
+    let data = std::fs::read(path)
+        .map_err(|e| Error::InvalidAttachment(format!("{} - {e}", path.display())))?;
+    Ok(format!(
+        "data:{media_type};base64,{}",
+        STANDARD.encode(data)
+    ))
+}
+
+/// Media type of file at `path` by extension
+fn media_type(kind: AttachmentKind, path: &Path) -> Result<&'static str, Error> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+
+    let media_type = match (kind, ext.as_str()) {
+        (AttachmentKind::Image, "png") => "image/png",
+        (AttachmentKind::Image, "jpg" | "jpeg") => "image/jpeg",
+        (AttachmentKind::Image, "gif") => "image/gif",
+        (AttachmentKind::Image, "webp") => "image/webp",
+        (AttachmentKind::Image, _) => {
+            return Err(Error::InvalidAttachment(format!(
+                "{} - unsupported image type, expected png, jpeg, gif, or webp",
+                path.display()
+            )));
+        }
+        (AttachmentKind::File, "pdf") => "application/pdf",
+        (AttachmentKind::File, "txt") => "text/plain",
+        (AttachmentKind::File, "md") => "text/markdown",
+        (AttachmentKind::File, "csv") => "text/csv",
+        (AttachmentKind::File, "json") => "application/json",
+        (AttachmentKind::File, "html" | "htm") => "text/html",
+        (AttachmentKind::File, _) => "application/octet-stream",
+    };
+    Ok(media_type)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn image_from_file() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("pixel.PNG")?;
+        file.write_binary(b"\x89PNG")?;
+
+        let image = Attachment::image(file.path().to_str().unwrap())?;
+        assert_eq!(image.url, "data:image/png;base64,iVBORw==");
+        assert_eq!(image.data(), Some(("image/png", "iVBORw==")));
+        assert_eq!(image.filename(), "pixel.PNG");
+        Ok(())
+    }
+
+    #[test]
+    fn image_from_url() -> Result<(), Error> {
+        let image = Attachment::image("https://example.com/cat.jpg")?;
+        assert_eq!(image.url, "https://example.com/cat.jpg");
+        assert_eq!(image.data(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn file_media_types() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        for (name, media_type) in [
+            ("report.pdf", "application/pdf"),
+            ("notes.md", "text/markdown"),
+            ("data.bin", "application/octet-stream"),
+        ] {
+            let file = dir.child(name);
+            file.write_str("hi")?;
+            let attachment = Attachment::file(file.path().to_str().unwrap())?;
+            assert_eq!(attachment.data(), Some((media_type, "aGk=")));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_image_type() {
+        let err = Attachment::image("photo.tiff").expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidAttachment(reason) if reason.contains("unsupported image type")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn local_files_relative_to_dir() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        dir.child("notes.txt").write_str("hi")?;
+
+        let attachment = Attachment::new(AttachmentKind::File, "notes.txt")
+            .load_with(&LocalFiles::Allow(dir.path().to_path_buf()))?;
+        assert_eq!(attachment.data(), Some(("text/plain", "aGk=")));
+        assert_eq!(attachment.source, "notes.txt");
+        Ok(())
+    }
+
+    #[test]
+    fn local_files_denied() -> Result<(), Error> {
+        let err = Attachment::new(AttachmentKind::File, "/etc/passwd")
+            .load_with(&LocalFiles::Deny)
+            .expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidAttachment(reason) if reason.contains("--allow-local-attachments")),
+            "unexpected error: {err:?}"
+        );
+
+        let image = Attachment::new(AttachmentKind::Image, "https://example.com/cat.jpg")
+            .load_with(&LocalFiles::Deny)?;
+        assert_eq!(image.url, "https://example.com/cat.jpg");
+        Ok(())
+    }
+
+    #[test]
+    fn file_url_is_rejected() {
+        assert!(matches!(
+            Attachment::file("https://example.com/report.pdf"),
+            Err(Error::InvalidAttachment(_))
+        ));
+    }
+
+    #[test]
+    fn missing_file() {
+        assert!(matches!(
+            Attachment::file("does_not_exist.pdf"),
+            Err(Error::InvalidAttachment(_))
+        ));
+    }
+
+    #[test]
+    fn oversized_file() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("large.pdf")?;
+        std::fs::File::create(file.path())?.set_len(MAX_SIZE + 1)?;
+
+        let err = Attachment::file(file.path().to_str().unwrap()).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidAttachment(reason) if reason.contains("exceeding limit")),
+            "unexpected error: {err:?}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+//! On-disk cache of responses, keyed by request payload
+//!
+//! Entries are stored as JSON under `$XDG_CACHE_HOME/cogni/responses/`, named by a SHA-256 hash of
+//! the provider, endpoint, and request payload. Entries older than the cache's TTL are ignored.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+use crate::openai::{Response, ResponseRequest};
+use crate::provider::{self, OnEvent, Provider, ProviderKind};
+
+/// Extension of cache entries
+const EXTENSION: &str = "json";
+
+/// Directory of cache, `cogni` under `$XDG_CACHE_HOME` or `~/.cache`
+pub fn dir() -> Option<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(cache_home.join("cogni"))
+}
+
+/// Directory that responses are cached in
+fn responses_dir() -> Result<PathBuf, Error> {
+    dir()
+        .map(|dir| dir.join("responses"))
+        .ok_or_else(|| Error::InvalidCache("neither XDG_CACHE_HOME nor HOME is set".to_string()))
+}
+
+/// Cache of responses in `dir`, with entries expiring after `ttl`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+/// Summary of cache contents shown by `cache stats`
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Stats {
+    pub dir: String,
+    /// Number of cached responses
+    pub entries: usize,
+    /// Total size of cached responses in bytes
+    pub size: u64,
+}
+
+impl Cache {
+    /// Cache in `dir()` with entries expiring after `ttl`
+    pub fn new(ttl: Duration) -> Result<Self, Error> {
+        Ok(Self::with_dir(responses_dir()?, ttl))
+    }
+
+    /// Cache in `dir` with entries expiring after `ttl`
+    pub fn with_dir(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// Key for `request` sent to provider of `kind` at `endpoint`
+    pub fn key(kind: ProviderKind, endpoint: &str, request: &ResponseRequest) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(kind.as_str());
+        hasher.update("\n");
+        hasher.update(endpoint);
+        hasher.update("\n");
+        hasher.update(request.to_payload().to_string());
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.{EXTENSION}"))
+    }
+
+    /// Cached response for `key`, marked as `cached`. `None` if missing or expired
+    pub fn get(&self, key: &str) -> Result<Option<Response>, Error> {
+        let path = self.path(key);
+        let modified = match std::fs::metadata(&path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::IO(e)),
+        };
+
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age > self.ttl {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        match serde_json::from_str::<Response>(&content) {
+            Ok(response) => Ok(Some(Response {
+                cached: true,
+                ..response
+            })),
+            Err(e) => {
+                log::warn!("ignoring invalid cache entry {} - {e}", path.display());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Store `response` as `key`. Written to a temporary file that is renamed over the entry, so
+    /// concurrent invocations never observe a partially written entry
+    pub fn put(&self, key: &str, response: &Response) -> Result<(), Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp = self.dir.join(format!(
+            ".{key}.{}.{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let response = Response {
+            cached: false,
+            ..response.clone()
+        };
+        std::fs::write(&tmp, serde_json::to_vec(&response)?)?;
+        std::fs::rename(&tmp, self.path(key)).inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })?;
+        Ok(())
+    }
+}
+
+/// Cache entries in `dir`
+fn entries(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(Error::IO(e)),
+    };
+
+    let mut paths = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if path.extension().is_some_and(|ext| ext == EXTENSION) && !hidden {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Number and size of cached responses
+pub fn stats() -> Result<Stats, Error> {
+    let dir = responses_dir()?;
+    let mut stats = Stats {
+        dir: dir.display().to_string(),
+        ..Stats::default()
+    };
+    for path in entries(&dir)? {
+        stats.entries += 1;
+        stats.size += std::fs::metadata(path)?.len();
+    }
+    Ok(stats)
+}
+
+/// Remove all cached responses, returning number removed
+pub fn clear() -> Result<usize, Error> {
+    let paths = entries(&responses_dir()?)?;
+    for path in &paths {
+        std::fs::remove_file(path)?;
+    }
+    Ok(paths.len())
+}
+
+/// Provider serving responses from cache, and caching responses created by `inner`
+pub(crate) struct CachedProvider {
+    inner: Box<dyn Provider>,
+    cache: Cache,
+    kind: ProviderKind,
+    endpoint: String,
+}
+
+impl CachedProvider {
+    pub(crate) fn new(
+        inner: Box<dyn Provider>,
+        cache: Cache,
+        kind: ProviderKind,
+        endpoint: String,
+    ) -> Self {
+        Self {
+            inner,
+            cache,
+            kind,
+            endpoint,
+        }
+    }
+
+    fn key(&self, request: &ResponseRequest) -> String {
+        Cache::key(self.kind, &self.endpoint, request)
+    }
+
+    /// Cache `response` as `key`. Failures are logged, since the response is still usable
+    fn put(&self, key: &str, response: &Response) {
+        if let Err(e) = self.cache.put(key, response) {
+            log::warn!("failed to cache response - {e}");
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CachedProvider {
+    async fn create_response(&self, request: &ResponseRequest) -> Result<Response, Error> {
+        let key = self.key(request);
+        if let Some(response) = self.cache.get(&key)? {
+            return Ok(response);
+        }
+
+        let response = self.inner.create_response(request).await?;
+        self.put(&key, &response);
+        Ok(response)
+    }
+
+    async fn stream_response(
+        &self,
+        request: &ResponseRequest,
+        on_event: &mut OnEvent<'_>,
+    ) -> Result<Response, Error> {
+        let key = self.key(request);
+        if let Some(response) = self.cache.get(&key)? {
+            provider::replay(&response, on_event)?;
+            return Ok(response);
+        }
+
+        let response = self.inner.stream_response(request, on_event).await?;
+        self.put(&key, &response);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::openai::{Choice, FinishReason, Message, Usage};
+
+    fn request(content: &str) -> ResponseRequest {
+        ResponseRequest::builder()
+            .model("gpt-5".to_string())
+            .messages(vec![Message::user(content)])
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("request builds")
+    }
+
+    fn response() -> Response {
+        Response {
+            id: Some("resp_1".to_string()),
+            created: DateTime::default(),
+            choices: vec![Choice {
+                message: Message::assistant("Hello"),
+                finish_reason: FinishReason::Stop,
+            }],
+            model: "gpt-5".to_string(),
+            usage: Usage::default(),
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn key_depends_on_payload_provider_and_endpoint() {
+        let key = Cache::key(
+            ProviderKind::OpenAI,
+            "https://api.openai.com",
+            &request("Hi"),
+        );
+
+        assert_eq!(key.len(), 64);
+        assert_eq!(
+            key,
+            Cache::key(
+                ProviderKind::OpenAI,
+                "https://api.openai.com",
+                &request("Hi")
+            )
+        );
+        assert_ne!(
+            key,
+            Cache::key(
+                ProviderKind::OpenAI,
+                "https://api.openai.com",
+                &request("Bye")
+            )
+        );
+        assert_ne!(
+            key,
+            Cache::key(
+                ProviderKind::Ollama,
+                "https://api.openai.com",
+                &request("Hi")
+            )
+        );
+        assert_ne!(
+            key,
+            Cache::key(
+                ProviderKind::OpenAI,
+                "http://localhost:8080",
+                &request("Hi")
+            )
+        );
+    }
+
+    #[test]
+    fn get_cached_response() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        let cache = Cache::with_dir(dir.path(), Duration::from_secs(60));
+
+        assert!(cache.get("abc")?.is_none());
+
+        cache.put("abc", &response())?;
+        let cached = cache.get("abc")?.expect("should be cached");
+        assert!(cached.cached);
+        assert_eq!(cached.id.as_deref(), Some("resp_1"));
+        assert_eq!(cached.choices, response().choices);
+        Ok(())
+    }
+
+    #[test]
+    fn get_expired_response() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        let cache = Cache::with_dir(dir.path(), Duration::ZERO);
+
+        cache.put("abc", &response())?;
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(cache.get("abc")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn get_invalid_entry() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = assert_fs::TempDir::new()?;
+        std::fs::write(dir.path().join("abc.json"), "{")?;
+
+        let cache = Cache::with_dir(dir.path(), Duration::from_secs(60));
+        assert!(cache.get("abc")?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+//! Recordings of API interactions, for running scripts deterministically without network access
+//!
+//! With `COGNI_RECORD=path`, each request payload is appended to the cassette at `path` along with
+//! the raw response, one JSON object per line. With `COGNI_REPLAY=path`, responses are served from
+//! the cassette instead of the API, and requests without a recording are errors.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Error;
+
+/// Environment variable with path of cassette that interactions are recorded to
+pub const RECORD_ENV: &str = "COGNI_RECORD";
+
+/// Environment variable with path of cassette that responses are replayed from
+pub const REPLAY_ENV: &str = "COGNI_REPLAY";
+
+/// Request and raw response recorded in cassette
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Request payload
+    pub request: Value,
+    /// HTTP status of response
+    pub status: u16,
+    /// Response body. Server-sent events for streamed responses
+    pub body: String,
+}
+
+/// Cassette that interactions are recorded to or replayed from
+#[derive(Debug)]
+pub enum Cassette {
+    Record {
+        path: PathBuf,
+        file: Mutex<File>,
+    },
+    Replay {
+        path: PathBuf,
+        interactions: Vec<Interaction>,
+    },
+}
+
+/// Cassette per `COGNI_RECORD` or `COGNI_REPLAY`, if either is set
+pub fn from_env() -> Result<Option<Cassette>, Error> {
+    match (std::env::var_os(RECORD_ENV), std::env::var_os(REPLAY_ENV)) {
+        (Some(_), Some(_)) => Err(Error::InvalidCassette(format!(
+            "{RECORD_ENV} and {REPLAY_ENV} can't both be set"
+        ))),
+        (Some(path), None) => Cassette::record(path).map(Some),
+        (None, Some(path)) => Cassette::replay(path).map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+impl Cassette {
+    /// Cassette appending interactions to file at `path`
+    pub fn record(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| Error::InvalidCassette(format!("{} - {e}", path.display())))?;
+        Ok(Self::Record {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Cassette replaying interactions recorded in file at `path`
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| Error::InvalidCassette(format!("{} - {e}", path.display())))?;
+
+        let interactions = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    Error::InvalidCassette(format!("{} at line {} - {e}", path.display(), idx + 1))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::Replay { path, interactions })
+    }
+
+    /// Whether responses are served from cassette instead of the API
+    pub fn is_replay(&self) -> bool {
+        matches!(self, Self::Replay { .. })
+    }
+
+    /// Recorded interaction for `request`, when replaying. The first matching recording is used
+    pub fn find(&self, request: &Value) -> Result<Option<&Interaction>, Error> {
+        match self {
+            Self::Record { .. } => Ok(None),
+            Self::Replay { path, interactions } => interactions
+                .iter()
+                .find(|i| &i.request == request)
+                .map(Some)
+                .ok_or_else(|| Error::NoRecording(path.display().to_string())),
+        }
+    }
+
+    /// Append interaction to cassette, when recording
+    pub fn append(&self, request: &Value, status: u16, body: &str) -> Result<(), Error> {
+        let Self::Record { path, file } = self else {
+            return Ok(());
+        };
+
+        let interaction = Interaction {
+            request: request.clone(),
+            status,
+            body: body.to_string(),
+        };
+        let mut line = serde_json::to_vec(&interaction)?;
+        line.push(b'\n');
+
+        // Single write per interaction, so concurrent requests don't interleave lines
+        let mut file = file.lock().expect("cassette lock poisoned");
+        file.write_all(&line)
+            .map_err(|e| Error::InvalidCassette(format!("{} - {e}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn record_then_replay() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("cassette.ndjson")?;
+
+        let cassette = Cassette::record(file.path())?;
+        cassette.append(&json!({"model": "gpt-5", "input": "Hi"}), 200, "{\"id\":1}")?;
+        cassette.append(&json!({"model": "gpt-5", "input": "Bye"}), 429, "{}")?;
+        assert_eq!(cassette.find(&json!({}))?, None);
+
+        let cassette = Cassette::replay(file.path())?;
+        assert!(cassette.is_replay());
+        assert_eq!(
+            cassette.find(&json!({"input": "Bye", "model": "gpt-5"}))?,
+            Some(&Interaction {
+                request: json!({"model": "gpt-5", "input": "Bye"}),
+                status: 429,
+                body: "{}".to_string(),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replay_missing_recording() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("cassette.ndjson")?;
+        std::fs::write(file.path(), "")?;
+
+        let cassette = Cassette::replay(file.path())?;
+        let err = cassette
+            .find(&json!({"input": "Hi"}))
+            .expect_err("should fail");
+        assert!(
+            matches!(err, Error::NoRecording(_)),
+            "unexpected error: {err:?}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replay_invalid_cassette() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("cassette.ndjson")?;
+        std::fs::write(
+            file.path(),
+            "{\"request\": {}, \"status\": 200, \"body\": \"\"}\n\n{",
+        )?;
+
+        let err = Cassette::replay(file.path()).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidCassette(reason) if reason.contains("line 3")),
+            "unexpected error: {err:?}"
+        );
+        Ok(())
+    }
+}
@@ -1,10 +1,23 @@
 //! Command line interface for cogni
 
+use std::collections::BTreeMap;
 use std::time::Duration;
 
-use crate::openai::{Message, ReasoningEffort};
+use crate::Error;
+use crate::attachment::Attachment;
+use crate::config::{Config, Price, Settings};
+use crate::ledger::{Budget, GroupBy};
+use crate::openai::{Message, ReasoningEffort, TextFormat, Tool, Usage};
+use crate::parse::InputFormat;
+use crate::provider::ProviderKind;
+use crate::retry::RetryPolicy;
+use crate::schema;
+use crate::session;
+use crate::template::{self, Template};
+use crate::tools::{self, CommandTool};
 use clap::{
-    ArgGroup, ArgMatches, Command, ValueEnum, arg, builder::PossibleValue, command, value_parser,
+    ArgGroup, ArgMatches, Command, ValueEnum, arg, builder::PossibleValue, command,
+    parser::ValueSource, value_parser,
 };
 use derive_builder::Builder;
 
@@ -13,14 +26,179 @@ use derive_builder::Builder;
 pub struct Invocation {
     pub api_key: Option<String>,
     pub messages: Vec<Message>,
+    /// Shell command printing API key, run when `api_key` is not given
+    #[builder(default)]
+    pub api_key_cmd: Option<String>,
+    #[builder(default)]
+    pub provider: ProviderKind,
+    /// Base URL for provider's API from config
+    #[builder(default)]
+    pub endpoint: Option<String>,
+    /// Path of rules file that mock provider replies from
+    #[builder(default)]
+    pub mock_rules: Option<String>,
+    /// Config profile that settings were resolved from
+    #[builder(default)]
+    pub profile: Option<String>,
     pub model: String,
     #[builder(default)]
     pub temperature: Option<f32>,
     pub output_format: OutputFormat,
+    #[builder(default)]
+    pub input_format: InputFormat,
     pub file: String,
     pub timeout: Duration,
     #[builder(default)]
     pub reasoning_effort: Option<ReasoningEffort>,
+    /// Upper bound for tokens generated. Provider's default if `None`
+    #[builder(default)]
+    pub max_output_tokens: Option<u32>,
+    /// How responses cut short, e.g. by `max_output_tokens`, are handled
+    #[builder(default)]
+    pub on_incomplete: OnIncomplete,
+    #[builder(default)]
+    pub stream: bool,
+    #[builder(default)]
+    pub id: Option<String>,
+    #[builder(default)]
+    pub text_format: Option<TextFormat>,
+    #[builder(default)]
+    pub tools: Vec<Tool>,
+    /// Images and files attached to last user message
+    #[builder(default)]
+    pub attachments: Vec<Attachment>,
+    /// Whether messages from stdin and batch records may attach local files
+    #[builder(default)]
+    pub allow_local_attachments: bool,
+    #[builder(default)]
+    pub retry_policy: RetryPolicy,
+    /// Whether responses are served from and written to cache
+    #[builder(default)]
+    pub cache: bool,
+    /// Duration that cached responses are used for
+    #[builder(default)]
+    pub cache_ttl: Duration,
+    /// Template rendered into messages after those from flags
+    #[builder(default)]
+    pub template: Option<Template>,
+    /// Values for template variables
+    #[builder(default)]
+    pub vars: BTreeMap<String, String>,
+    /// Session that history is read from, and the exchange is appended to
+    #[builder(default)]
+    pub session: Option<String>,
+    /// Messages of `session` preceding messages from flags, template, and file
+    #[builder(default)]
+    pub history: Vec<Message>,
+    /// Token usage of requests in `session` so far
+    #[builder(default)]
+    pub history_usage: Usage,
+    /// Whether token usage is printed to stderr after reply
+    #[builder(default)]
+    pub usage: bool,
+    /// Price of `model` from config, for estimating cost printed with usage and recorded in ledger
+    #[builder(default)]
+    pub price: Option<Price>,
+    /// Limits on estimated spend, after which requests are refused
+    #[builder(default)]
+    pub budget: Budget,
+    /// Id of stored response to continue from
+    #[builder(default)]
+    pub previous_response_id: Option<String>,
+    /// Whether response is stored by provider. Provider's default if `None`
+    #[builder(default)]
+    pub store: Option<bool>,
+    /// Whether response id is printed to stderr
+    #[builder(default)]
+    pub print_id: bool,
+    /// File that response id is written to
+    #[builder(default)]
+    pub id_file: Option<String>,
+    #[builder(default)]
+    pub mode: Mode,
+}
+
+/// Modes that invocation can run in
+#[derive(Debug, Default, PartialEq, Clone)]
+pub enum Mode {
+    /// Single request for messages from flags and `file`
+    #[default]
+    Chat,
+    /// One request per record read from `file`
+    Batch(BatchArgs),
+    /// Requests in a loop, running tools the model calls as local commands
+    Agent(AgentArgs),
+    /// Prints effective settings
+    ShowConfig,
+    /// Manages stored sessions
+    Session(SessionCommand),
+    /// Manages response cache
+    Cache(CacheCommand),
+    /// Summarizes ledger of requests
+    Usage(UsageCommand),
+}
+
+/// Subcommands of `Mode::Usage`
+#[derive(Debug, PartialEq, Clone)]
+pub enum UsageCommand {
+    /// Prints totals of requests within `since`, grouped by `group_by`
+    Report {
+        since: Option<Duration>,
+        group_by: GroupBy,
+    },
+}
+
+/// Subcommands of `Mode::Cache`
+#[derive(Debug, PartialEq, Clone)]
+pub enum CacheCommand {
+    /// Prints location, number, and size of cached responses
+    Stats,
+    /// Removes cached responses
+    Clear,
+}
+
+/// Subcommands of `Mode::Session`
+#[derive(Debug, PartialEq, Clone)]
+pub enum SessionCommand {
+    /// Prints names of sessions
+    List,
+    /// Prints messages of session as transcript
+    Show(String),
+    /// Removes session
+    Remove(String),
+    /// Copies session to a new name
+    Fork { from: String, to: String },
+}
+
+/// Arguments for `Mode::Batch`
+#[derive(Debug, PartialEq, Clone)]
+pub struct BatchArgs {
+    /// Maximum number of requests in flight
+    pub concurrency: usize,
+    /// Whether results are written in input order, or as they complete
+    pub ordered: bool,
+    pub record_format: RecordFormat,
+}
+
+/// Arguments for `Mode::Agent`
+#[derive(Debug, PartialEq, Clone)]
+pub struct AgentArgs {
+    /// Tools available to model, run as local commands
+    pub tools: Vec<CommandTool>,
+    /// Maximum number of requests before giving up
+    pub max_steps: usize,
+    /// Whether each tool call is confirmed via terminal before running
+    pub confirm: bool,
+}
+
+/// The format that batch records are in
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum RecordFormat {
+    /// Each non-empty line is a user message
+    #[default]
+    Lines,
+    /// Each line is an object with optional `id`, and `input` text or `messages` array
+    NDJSON,
 }
 
 /// The format that invocation's results are in
@@ -30,18 +208,235 @@ pub enum OutputFormat {
     Plaintext,
     JSON,
     JSONPretty,
+    NDJSON,
+    Transcript,
+}
+
+/// How responses that are cut short by output token limit or content filter are handled
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum OnIncomplete {
+    /// Print truncated output, then exit with `Error::IncompleteResponse`
+    #[default]
+    Print,
+    /// Exit with `Error::IncompleteResponse` without printing output
+    Fail,
 }
 
-/// Parse commandline arguments into `Invocation`. May exit with help or error message
-#[must_use]
-pub fn parse() -> Invocation {
-    cli().get_matches().into()
+/// How errors are printed to stderr
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum ErrorFormat {
+    /// Error message with its causes
+    #[default]
+    Text,
+    /// `ErrorReport` as JSON object
+    JSON,
+}
+
+/// Parse commandline arguments. May exit with help or error message
+pub fn matches() -> ArgMatches {
+    cli().get_matches()
+}
+
+/// Format that errors are printed in, per `matches`
+pub fn error_format(matches: &ArgMatches) -> ErrorFormat {
+    matches
+        .get_one::<ErrorFormat>("error_format")
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Parse commandline arguments in `matches` into `Invocation`, with settings from config file
+pub fn parse(matches: ArgMatches) -> Result<Invocation, Error> {
+    let profile = command_matches(&matches).1.get_one::<String>("profile");
+    let script = match (
+        matches.subcommand(),
+        matches.get_one::<Template>("template"),
+        matches.get_one::<String>("file"),
+    ) {
+        (None, None, Some(file)) if file != "-" => template::load_script(file)?,
+        _ => None,
+    };
+
+    let session_name = matches.get_one::<String>("session");
+    let session = match session_name {
+        Some(name) => session::load(name)?,
+        None => None,
+    };
+
+    let config = Config::load()?;
+    let mut settings = config.resolve(profile.map(String::as_str))?;
+    if let Some(session) = &session {
+        settings = session.apply(settings);
+    }
+    if let Some(template) = script
+        .as_ref()
+        .or_else(|| matches.get_one::<Template>("template"))
+    {
+        settings = template.apply(settings);
+    }
+
+    let mut invocation = Invocation::from_matches(matches, &settings);
+    if let Some(script) = script {
+        // Script given as file is run as template, with stdin as its input
+        invocation.template = Some(script);
+        invocation.file = "-".to_string();
+    }
+    if let Some(session) = session {
+        invocation.history = session.messages;
+        invocation.history_usage = session.usage;
+    }
+    invocation.price = config.prices.get(&invocation.model).cloned();
+    Ok(invocation)
 }
 
 /// Top-level command
 fn cli() -> Command {
-    command!()
-        .arg(arg!(model: -m --model <MODEL> "Sets model. See https://platform.openai.com/docs/models for model identifiers.").default_value("gpt-5.5"))
+    chat_args(command!())
+        .arg(arg!(stream: --stream "Streams response to stdout as it is generated"))
+        .arg(arg!(id: --id <ID> "Sets caller-supplied id included in NDJSON output").required(false))
+        .arg(arg!(file: [FILE] "File providing messages to append to chat log. If \"-\", reads from non-tty stdin. Files starting with \"#!\" are run as templates, with stdin as input").default_value("-"))
+        .arg(
+            arg!(template: -f --template <TEMPLATE> "Renders template at path, or named in $COGNI_TEMPLATES or $XDG_CONFIG_HOME/cogni/templates. Contents of FILE are bound to {{input}}, and template front matter supplies settings like a profile")
+                .value_parser(template::load),
+        )
+        .arg(
+            arg!(vars: --var <KEY_VALUE> ... "Sets template variable, e.g. --var style=conventional")
+                .value_parser(parse_var)
+                .required(false),
+        )
+        .arg(arg!(previous_response_id: --continue <RESPONSE_ID> "Continues from stored response RESPONSE_ID, without resending its history. Only supported by openai provider"))
+        .arg(arg!(store: --store "Stores response, so it can be continued via --continue"))
+        .arg(arg!(print_id: --"print-id" "Prints response id to stderr"))
+        .arg(arg!(id_file: --"id-file" <FILE> "Writes response id to FILE"))
+        .arg(arg!(session: --session <NAME> "Continues session NAME, stored under $XDG_STATE_HOME/cogni/sessions. Input and reply are appended to it after each successful request"))
+        .arg(
+            arg!(error_format: --"error-format" <FORMAT> "Sets format of errors printed to stderr. JSON errors are objects with \"kind\", \"message\", \"status\", \"code\", and \"param\"")
+                .value_parser(value_parser!(ErrorFormat))
+                .default_value("text")
+                .global(true),
+        )
+        .subcommand(
+            chat_args(Command::new("batch"))
+                .about("Runs one request per record, sharing prompt and flags across records")
+                .arg(
+                    arg!(concurrency: -j --concurrency <N> "Sets maximum number of requests in flight")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("4"),
+                )
+                .arg(arg!(unordered: --unordered "Writes results as they complete instead of in input order. Plaintext results are prefixed with record id and a tab"))
+                .arg(
+                    arg!(record_format: --records <FORMAT> "Sets format of records. NDJSON records are objects with optional \"id\", and \"input\" text or \"messages\" array")
+                        .value_parser(value_parser!(RecordFormat))
+                        .default_value("lines"),
+                )
+                .arg(arg!(file: [FILE] "File providing records. If \"-\", reads from stdin").default_value("-")),
+        )
+        .subcommand(
+            chat_args(Command::new("agent"))
+                .about("Runs model in a loop, executing tools it calls as local commands until it replies with a message")
+                .arg(
+                    arg!(config: -c --config <FILE> "Sets JSON array of tools, each with \"name\", \"description\", \"parameters\" schema, and \"command\". Placeholders like {{param}} in commands are replaced by shell-quoted arguments")
+                        .value_parser(tools::load_commands)
+                        .required(true),
+                )
+                .mut_arg("tools", |a| a.conflicts_with("config"))
+                .arg(
+                    arg!(max_steps: --"max-steps" <N> "Sets maximum number of requests before giving up")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("10"),
+                )
+                .arg(arg!(yes: -y --yes "Runs tool commands without confirmation"))
+                .arg(arg!(file: [FILE] "File providing messages to append to chat log. If \"-\", reads from non-tty stdin").default_value("-")),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Inspects configuration, read from $XDG_CONFIG_HOME/cogni/config.toml or $COGNI_CONFIG")
+                .subcommand_required(true)
+                .subcommand(
+                    chat_args(Command::new("show"))
+                        .about("Prints effective settings after merging config, profile, environment, and flags. API key is redacted"),
+                ),
+        )
+        .subcommand(
+            Command::new("session")
+                .about("Manages sessions created via --session")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("Prints names of sessions"))
+                .subcommand(
+                    Command::new("show")
+                        .about("Prints messages of session as transcript")
+                        .arg(arg!(name: <NAME> "Session name")),
+                )
+                .subcommand(
+                    Command::new("rm")
+                        .about("Removes session")
+                        .arg(arg!(name: <NAME> "Session name")),
+                )
+                .subcommand(
+                    Command::new("fork")
+                        .about("Copies session to a new session, to continue it separately")
+                        .arg(arg!(from: <FROM> "Session to copy"))
+                        .arg(arg!(to: <TO> "Name of new session")),
+                ),
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Manages response cache enabled via --cache, stored under $XDG_CACHE_HOME/cogni")
+                .subcommand_required(true)
+                .subcommand(Command::new("stats").about("Prints location, number, and size of cached responses"))
+                .subcommand(Command::new("clear").about("Removes cached responses")),
+        )
+        .subcommand(
+            Command::new("usage")
+                .about("Summarizes ledger of requests, stored under $XDG_STATE_HOME/cogni")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("report")
+                        .about("Prints number of requests, tokens, and estimated cost per group")
+                        .arg(
+                            arg!(since: --since <DURATION> "Includes requests within DURATION, e.g. 30m, 24h, 7d, or 4w")
+                                .value_parser(parse_since),
+                        )
+                        .arg(
+                            arg!(group_by: --"group-by" <KEY> "Groups requests by model, provider, profile, or day")
+                                .value_parser(value_parser!(GroupBy))
+                                .default_value("model"),
+                        ),
+                ),
+        )
+}
+
+/// Mode and matches for arguments of the invoked command
+fn command_matches(matches: &ArgMatches) -> (Mode, &ArgMatches) {
+    match matches.subcommand() {
+        Some(("batch", sub)) => (Mode::Batch(BatchArgs::from(sub)), sub),
+        Some(("agent", sub)) => (Mode::Agent(AgentArgs::from(sub)), sub),
+        Some(("config", sub)) => match sub.subcommand() {
+            Some(("show", show)) => (Mode::ShowConfig, show),
+            _ => unreachable!("config subcommand is required"),
+        },
+        Some(("session", sub)) => (Mode::Session(SessionCommand::from(sub)), matches),
+        Some(("cache", sub)) => (Mode::Cache(CacheCommand::from(sub)), matches),
+        Some(("usage", sub)) => (Mode::Usage(UsageCommand::from(sub)), matches),
+        _ => (Mode::Chat, matches),
+    }
+}
+
+/// Arguments for model, messages, and formats shared across commands
+fn chat_args(cmd: Command) -> Command {
+    cmd.arg(
+            arg!(profile: --profile <NAME> "Sets config profile supplying defaults for model, provider, and other settings")
+                .env("COGNI_PROFILE"),
+        )
+        .arg(arg!(model: -m --model <MODEL> "Sets model. See https://platform.openai.com/docs/models for model identifiers. May be prefixed by provider, e.g. ollama:llama3").default_value("gpt-5.5"))
+        .arg(
+            arg!(provider: --provider <PROVIDER> "Sets provider. chat-completions uses OpenAI-compatible /v1/chat/completions at OPENAI_API_ENDPOINT with CHAT_COMPLETIONS_API_KEY, ollama uses OLLAMA_HOST, anthropic uses ANTHROPIC_API_KEY and ANTHROPIC_API_ENDPOINT, and mock replies per --mock-rules")
+                .value_parser(value_parser!(ProviderKind)),
+        )
+        .arg(
+            arg!(mock_rules: --"mock-rules" <FILE> "Sets rules file that mock provider replies from. Without rules, mock provider echoes input")
+                .env("COGNI_MOCK_RULES"),
+        )
         .arg(
             arg!(temperature: -t --temperature <TEMP> "Sets temperature")
                 .value_parser(value_parser!(f32)),
@@ -63,7 +458,7 @@ fn cli() -> Command {
                 .hide_env_values(true),
         )
         .arg(
-            arg!(output_format: --output_format <FORMAT> "Sets output format")
+            arg!(output_format: -o --output_format <FORMAT> "Sets output format")
                 .value_parser(value_parser!(OutputFormat))
                 .conflicts_with("output_format_short")
                 .default_value_ifs([
@@ -72,55 +467,354 @@ fn cli() -> Command {
                 ])
                 .default_value("plaintext"),
         )
+        .arg(
+            arg!(input_format: -i --"input-format" <FORMAT> "Sets format of messages read from FILE. auto reads FILE as transcript if it begins with a role marker line like \">>> user\"")
+                .value_parser(value_parser!(InputFormat))
+                .default_value("text"),
+        )
         .arg(
             arg!(reasoning_effort: --"reasoning-effort" <EFFORT> "Sets reasoning effort (low, medium, high)")
                 .value_parser(value_parser!(ReasoningEffort))
                 .required(false),
         )
+        .arg(
+            arg!(max_output_tokens: --"max-output-tokens" <N> "Sets maximum number of tokens generated, including reasoning tokens")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            arg!(on_incomplete: --"on-incomplete" <ACTION> "Sets whether responses cut short by --max-output-tokens or content filter are printed (print) or discarded (fail). Either way, cogni exits with status 3. With fail, --stream is ignored")
+                .value_parser(value_parser!(OnIncomplete))
+                .default_value("print"),
+        )
+        .arg(
+            arg!(schema: --schema <FILE> "Requests output adhering to JSON Schema in FILE. Output is validated before printing, so --stream is ignored")
+                .value_parser(schema::load)
+                .conflicts_with("json_object"),
+        )
+        .arg(arg!(json_object: --"json-object" "Requests output as a JSON object. Output is validated before printing, so --stream is ignored"))
+        .arg(
+            arg!(tools: --tools <FILE> "Offers function tools defined in JSON array in FILE. Tool calls are printed as tab-separated CALL_ID, NAME, ARGUMENTS lines in plaintext output")
+                .value_parser(tools::load),
+        )
+        .arg(
+            arg!(images: --image <SOURCE> ... "Attaches image at path or URL to last user message. Local images are sent base64-encoded")
+                .value_parser(Attachment::image)
+                .required(false),
+        )
+        .arg(
+            arg!(attached_files: --file <PATH> ... "Attaches file, e.g. a PDF, to last user message")
+                .value_parser(Attachment::file)
+                .required(false),
+        )
+        .arg(arg!(allow_local_attachments: --"allow-local-attachments" "Allows messages read from stdin or batch records to attach local files, e.g. via \">>> file <PATH>\". Messages in FILE may always attach files, with paths relative to FILE"))
+        .arg(arg!(cache: --cache "Serves repeated identical requests from cache under $XDG_CACHE_HOME/cogni, instead of resending them").overrides_with("no_cache"))
+        .arg(arg!(no_cache: --"no-cache" "Disables cache enabled via config").overrides_with("cache"))
+        .arg(
+            arg!(cache_ttl: --"cache-ttl" <SECONDS> "Sets seconds that cached responses are used for")
+                .value_parser(value_parser!(u64))
+                .default_value("604800"),
+        )
+        .arg(
+            arg!(retries: --retries <N> "Sets number of retries for rate limited, server error, connection, and timed out failures. Timed out requests may still be processed and billed")
+                .value_parser(value_parser!(u32))
+                .default_value("2"),
+        )
+        .arg(
+            arg!(max_backoff: --"max-backoff" <DURATION> "Sets maximum delay between retries in seconds")
+                .value_parser(value_parser!(u64))
+                .default_value("30"),
+        )
+        .arg(arg!(usage: --usage "Prints token usage to stderr after reply, with estimated cost if config has prices for model. Batch and agent print totals over all requests, and sessions also print the session's total"))
+        .arg(
+            arg!(budget: --budget <USD> "Refuses to send requests once their estimated cost reaches USD. Daily and monthly limits are set via daily_budget and monthly_budget in config")
+                .value_parser(value_parser!(f64)),
+        )
         .arg(arg!(--json "Shorthand for --output_format json"))
         .arg(arg!(--jsonp "Shorthand for --output_format jsonpretty"))
         .group(ArgGroup::new("output_format_short").args(["json", "jsonp"]))
-        .arg(arg!(file: [FILE] "File providing messages to append to chat log. If \"-\", reads from non-tty stdin").default_value("-"))
 }
 
 impl From<ArgMatches> for Invocation {
     fn from(matches: ArgMatches) -> Self {
-        let api_key = matches.get_one::<String>("api_key").cloned();
-        let messages = Invocation::messages_from_matches(&matches);
-        let model = matches
-            .get_one::<String>("model")
-            .expect("Models is required")
-            .to_string();
-
-        let temperature = matches.get_one::<f32>("temperature").copied();
-
-        let timeout = matches
-            .get_one::<u64>("timeout")
-            .map(|t| Duration::from_secs(*t))
+        Invocation::from_matches(matches, &Settings::default())
+    }
+}
+
+impl Invocation {
+    /// Create invocation from `matches`, with `settings` from config used for values not given
+    /// via flags or environment
+    pub fn from_matches(matches: ArgMatches, settings: &Settings) -> Self {
+        let stream = matches.get_flag("stream");
+        let id = matches.get_one::<String>("id").cloned();
+        let template = matches.get_one::<Template>("template").cloned();
+        let session = matches.get_one::<String>("session").cloned();
+        let previous_response_id = matches.get_one::<String>("previous_response_id").cloned();
+        let store = matches.get_flag("store").then_some(true);
+        let print_id = matches.get_flag("print_id");
+        let id_file = matches.get_one::<String>("id_file").cloned();
+        let vars = matches
+            .get_many::<(String, String)>("vars")
+            .map(|vars| vars.cloned().collect())
+            .unwrap_or_default();
+
+        let (mode, matches) = command_matches(&matches);
+
+        let mut messages = Invocation::messages_from_matches(matches);
+        if let (None, Some(system)) = (
+            matches.get_one::<String>("system_message"),
+            &settings.system,
+        ) {
+            messages.insert(0, Message::system(system));
+        }
+
+        let model =
+            explicit_or(matches, "model", settings.model.clone()).expect("Models is required");
+
+        // Provider prefix of model takes precedence over --provider
+        let (prefix, model) = ProviderKind::split_model(&model);
+        let provider = prefix
+            .or(explicit_or(matches, "provider", settings.provider))
+            .unwrap_or_default();
+        let model = model.to_string();
+
+        // Providers with their own key environment variable don't use OPENAI_API_KEY
+        let api_key = match provider.api_key_env() {
+            Some(env) if matches.value_source("api_key") != Some(ValueSource::CommandLine) => {
+                std::env::var(env).ok()
+            }
+            _ => matches.get_one::<String>("api_key").cloned(),
+        };
+
+        let temperature = explicit_or(matches, "temperature", settings.temperature);
+
+        let timeout = explicit_or(matches, "timeout", settings.timeout)
+            .map(Duration::from_secs)
             .expect("Timeout is required");
 
         let output_format = *matches
             .get_one::<OutputFormat>("output_format")
             .expect("Output format is required");
 
-        let file = matches
-            .get_one::<String>("file")
-            .expect("File is required")
-            .to_string();
+        let input_format = *matches
+            .get_one::<InputFormat>("input_format")
+            .expect("Input format is required");
+
+        // `config show` reads no messages
+        let file = match matches.try_get_one::<String>("file") {
+            Ok(Some(file)) => file.to_string(),
+            _ => "-".to_string(),
+        };
+
+        let reasoning_effort = explicit_or(matches, "reasoning_effort", settings.reasoning_effort);
+
+        let max_output_tokens = matches.get_one::<u32>("max_output_tokens").copied();
+
+        let on_incomplete = *matches
+            .get_one::<OnIncomplete>("on_incomplete")
+            .expect("On incomplete is required");
+
+        let text_format = match matches.get_one::<TextFormat>("schema") {
+            Some(format) => Some(format.clone()),
+            None if matches.get_flag("json_object") => Some(TextFormat::JsonObject),
+            None => None,
+        };
+
+        let tools = match &mode {
+            Mode::Agent(agent) => agent.tools.iter().map(|t| t.tool.clone()).collect(),
+            _ => matches
+                .get_one::<Vec<Tool>>("tools")
+                .cloned()
+                .unwrap_or_default(),
+        };
+
+        let attachments = ["images", "attached_files"]
+            .into_iter()
+            .flat_map(|id| matches.get_many::<Attachment>(id).into_iter().flatten())
+            .cloned()
+            .collect();
+
+        // Flags take precedence over config, with the last of `--cache` and `--no-cache` winning
+        let cache = match (matches.get_flag("cache"), matches.get_flag("no_cache")) {
+            (true, _) => true,
+            (_, true) => false,
+            _ => settings.cache.unwrap_or(false),
+        };
 
-        let reasoning_effort = matches
-            .get_one::<ReasoningEffort>("reasoning_effort")
-            .copied();
+        let cache_ttl = explicit_or(matches, "cache_ttl", settings.cache_ttl)
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+
+        let retry_policy = RetryPolicy {
+            max_retries: *matches
+                .get_one::<u32>("retries")
+                .expect("Retries is required"),
+            max_backoff: matches
+                .get_one::<u64>("max_backoff")
+                .map(|t| Duration::from_secs(*t))
+                .expect("Max backoff is required"),
+            ..RetryPolicy::default()
+        };
 
         Self {
             api_key,
+            api_key_cmd: settings.api_key_cmd.clone(),
             messages,
+            provider,
+            endpoint: settings.endpoint.clone(),
+            mock_rules: explicit_or(matches, "mock_rules", settings.mock_rules.clone()),
+            profile: matches.get_one::<String>("profile").cloned(),
             model,
             temperature,
             timeout,
             output_format,
+            input_format,
             file,
             reasoning_effort,
+            max_output_tokens,
+            on_incomplete,
+            stream,
+            id,
+            text_format,
+            tools,
+            attachments,
+            allow_local_attachments: matches.get_flag("allow_local_attachments"),
+            retry_policy,
+            cache,
+            cache_ttl,
+            template,
+            vars,
+            session,
+            history: vec![],
+            history_usage: Usage::default(),
+            usage: matches.get_flag("usage"),
+            price: None,
+            budget: Budget {
+                invocation: matches.get_one::<f64>("budget").copied(),
+                daily: settings.daily_budget,
+                monthly: settings.monthly_budget,
+            },
+            previous_response_id,
+            store,
+            print_id,
+            id_file,
+            mode,
+        }
+    }
+}
+
+/// Parse `KEY=VALUE` template variable
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got \"{s}\"")),
+    }
+}
+
+/// Parse duration with unit, e.g. `30m`, `24h`, `7d`, or `4w`
+fn parse_since(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("expected number followed by s, m, h, d, or w, got \"{s}\"");
+    let unit_idx = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (count, unit) = s.split_at(unit_idx);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    count
+        .checked_mul(secs)
+        .map(Duration::from_secs)
+        .filter(|duration| chrono::TimeDelta::from_std(*duration).is_ok())
+        .ok_or_else(|| format!("duration \"{s}\" is too large"))
+}
+
+/// Value of `id` if given via flag or environment, otherwise `setting` from config, otherwise
+/// default value of `id`
+fn explicit_or<T: Clone + Send + Sync + 'static>(
+    matches: &ArgMatches,
+    id: &str,
+    setting: Option<T>,
+) -> Option<T> {
+    match matches.value_source(id) {
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable) => {
+            matches.get_one::<T>(id).cloned()
+        }
+        _ => setting.or_else(|| matches.get_one::<T>(id).cloned()),
+    }
+}
+
+impl From<&ArgMatches> for AgentArgs {
+    fn from(matches: &ArgMatches) -> Self {
+        Self {
+            tools: matches
+                .get_one::<Vec<CommandTool>>("config")
+                .expect("Config is required")
+                .clone(),
+            max_steps: *matches
+                .get_one::<u32>("max_steps")
+                .expect("Max steps is required") as usize,
+            confirm: !matches.get_flag("yes"),
+        }
+    }
+}
+
+impl From<&ArgMatches> for SessionCommand {
+    fn from(matches: &ArgMatches) -> Self {
+        let name = |sub: &ArgMatches, id: &str| {
+            sub.get_one::<String>(id)
+                .expect("Session name is required")
+                .clone()
+        };
+        match matches.subcommand() {
+            Some(("list", _)) => Self::List,
+            Some(("show", sub)) => Self::Show(name(sub, "name")),
+            Some(("rm", sub)) => Self::Remove(name(sub, "name")),
+            Some(("fork", sub)) => Self::Fork {
+                from: name(sub, "from"),
+                to: name(sub, "to"),
+            },
+            _ => unreachable!("session subcommand is required"),
+        }
+    }
+}
+
+impl From<&ArgMatches> for CacheCommand {
+    fn from(matches: &ArgMatches) -> Self {
+        match matches.subcommand() {
+            Some(("stats", _)) => Self::Stats,
+            Some(("clear", _)) => Self::Clear,
+            _ => unreachable!("cache subcommand is required"),
+        }
+    }
+}
+
+impl From<&ArgMatches> for UsageCommand {
+    fn from(matches: &ArgMatches) -> Self {
+        match matches.subcommand() {
+            Some(("report", sub)) => Self::Report {
+                since: sub.get_one::<Duration>("since").copied(),
+                group_by: *sub
+                    .get_one::<GroupBy>("group_by")
+                    .expect("Group by is required"),
+            },
+            _ => unreachable!("usage subcommand is required"),
+        }
+    }
+}
+
+impl From<&ArgMatches> for BatchArgs {
+    fn from(matches: &ArgMatches) -> Self {
+        Self {
+            concurrency: *matches
+                .get_one::<u32>("concurrency")
+                .expect("Concurrency is required") as usize,
+            ordered: !matches.get_flag("unordered"),
+            record_format: *matches
+                .get_one::<RecordFormat>("record_format")
+                .expect("Record format is required"),
         }
     }
 }
@@ -149,7 +843,7 @@ impl Invocation {
                     .zip(matches.indices_of("assistant_messages").unwrap()),
             );
         }
-        messages.sort_by(|(_a, a_idx), (_b, b_idx)| a_idx.cmp(b_idx));
+        messages.sort_by_key(|(_a, idx)| *idx);
         let mut messages = messages.into_iter().map(|(a, _)| a).collect::<Vec<_>>();
 
         // System message is always first
@@ -163,7 +857,13 @@ impl Invocation {
 
 impl ValueEnum for OutputFormat {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Plaintext, Self::JSON, Self::JSONPretty]
+        &[
+            Self::Plaintext,
+            Self::JSON,
+            Self::JSONPretty,
+            Self::NDJSON,
+            Self::Transcript,
+        ]
     }
 
     fn to_possible_value(&self) -> Option<PossibleValue> {
@@ -171,6 +871,100 @@ impl ValueEnum for OutputFormat {
             Self::Plaintext => PossibleValue::new("plaintext"),
             Self::JSON => PossibleValue::new("json"),
             Self::JSONPretty => PossibleValue::new("jsonpretty"),
+            Self::NDJSON => PossibleValue::new("ndjson"),
+            Self::Transcript => PossibleValue::new("transcript"),
+        })
+    }
+}
+
+impl ValueEnum for InputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Auto,
+            Self::Text,
+            Self::Transcript,
+            Self::JSON,
+            Self::NDJSON,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Self::Auto => PossibleValue::new("auto"),
+            Self::Text => PossibleValue::new("text"),
+            Self::Transcript => PossibleValue::new("transcript"),
+            Self::JSON => PossibleValue::new("json"),
+            Self::NDJSON => PossibleValue::new("ndjson"),
+        })
+    }
+}
+
+impl ValueEnum for RecordFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Lines, Self::NDJSON]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Self::Lines => PossibleValue::new("lines"),
+            Self::NDJSON => PossibleValue::new("ndjson"),
+        })
+    }
+}
+
+impl ValueEnum for GroupBy {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Model, Self::Provider, Self::Profile, Self::Day]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Self::Model => PossibleValue::new("model"),
+            Self::Provider => PossibleValue::new("provider"),
+            Self::Profile => PossibleValue::new("profile"),
+            Self::Day => PossibleValue::new("day"),
+        })
+    }
+}
+
+impl ValueEnum for ProviderKind {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::OpenAI,
+            Self::ChatCompletions,
+            Self::Ollama,
+            Self::Anthropic,
+            Self::Mock,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(PossibleValue::new(self.as_str()))
+    }
+}
+
+impl ValueEnum for ErrorFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Text, Self::JSON]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Self::Text => PossibleValue::new("text"),
+            Self::JSON => PossibleValue::new("json"),
+        })
+    }
+}
+
+impl ValueEnum for OnIncomplete {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Print, Self::Fail]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Self::Print => PossibleValue::new("print"),
+            Self::Fail => PossibleValue::new("fail"),
         })
     }
 }
@@ -192,6 +986,7 @@ impl ValueEnum for ReasoningEffort {
 #[cfg(test)]
 mod test {
     use super::*;
+    use assert_fs::prelude::*;
 
     type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -223,6 +1018,45 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn chat_provider_default() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-m", "gpt-5"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.provider, ProviderKind::OpenAI);
+        assert_eq!(args.model, "gpt-5");
+        Ok(())
+    }
+
+    #[test]
+    fn chat_provider_flag() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec![
+                "cogni",
+                "--provider",
+                "chat-completions",
+                "-m",
+                "qwen",
+            ])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.provider, ProviderKind::ChatCompletions);
+        assert_eq!(args.model, "qwen");
+        Ok(())
+    }
+
+    #[test]
+    fn chat_provider_model_prefix() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-m", "ollama:llama3:8b"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.provider, ProviderKind::Ollama);
+        assert_eq!(args.model, "llama3:8b");
+        Ok(())
+    }
+
     #[test]
     fn chat_reasoning_effort_flag() -> Result<()> {
         let args = cli()
@@ -233,6 +1067,33 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn chat_max_output_tokens_flag() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-u", "USER"])
+            .map(Invocation::from)?;
+        assert_eq!(args.max_output_tokens, None);
+        assert_eq!(args.on_incomplete, OnIncomplete::Print);
+
+        let args = cli()
+            .try_get_matches_from(vec![
+                "cogni",
+                "-u",
+                "USER",
+                "--max-output-tokens",
+                "256",
+                "--on-incomplete",
+                "fail",
+            ])
+            .map(Invocation::from)?;
+        assert_eq!(args.max_output_tokens, Some(256));
+        assert_eq!(args.on_incomplete, OnIncomplete::Fail);
+
+        let res = cli().try_get_matches_from(vec!["cogni", "--max-output-tokens", "0"]);
+        assert!(res.is_err(), "zero max output tokens should be rejected");
+        Ok(())
+    }
+
     #[test]
     fn chat_many_msgs_with_system_prompt() -> Result<()> {
         let args = cli()
@@ -320,6 +1181,57 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn chat_input_format_default() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.input_format, InputFormat::Text);
+        Ok(())
+    }
+
+    #[test]
+    fn chat_input_format_transcript() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "--input-format", "transcript"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.input_format, InputFormat::Transcript);
+        Ok(())
+    }
+
+    #[test]
+    fn chat_stream_flag() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-u", "ABC", "--stream"])
+            .map(Invocation::from)?;
+
+        assert!(args.stream);
+        Ok(())
+    }
+
+    #[test]
+    fn chat_output_format_transcript_short() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-o", "transcript"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.output_format, OutputFormat::Transcript);
+        Ok(())
+    }
+
+    #[test]
+    fn chat_output_format_ndjson_with_id() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-o", "ndjson", "--id", "ID"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.output_format, OutputFormat::NDJSON);
+        assert_eq!(args.id, Some("ID".to_string()));
+        Ok(())
+    }
+
     #[test]
     fn chat_file_default() -> Result<()> {
         let args = cli()
@@ -339,4 +1251,429 @@ mod test {
         assert_eq!(args.file, "dialog_log");
         Ok(())
     }
+
+    #[test]
+    fn chat_retry_policy() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "--retries", "5", "--max-backoff", "10"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.retry_policy.max_retries, 5);
+        assert_eq!(args.retry_policy.max_backoff, Duration::from_secs(10));
+        Ok(())
+    }
+
+    #[test]
+    fn chat_retry_policy_default() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.retry_policy, RetryPolicy::default());
+        Ok(())
+    }
+
+    #[test]
+    fn chat_json_object_flag() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "--json-object"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.text_format, Some(TextFormat::JsonObject));
+        Ok(())
+    }
+
+    #[test]
+    fn chat_schema_file_missing() {
+        let res = cli().try_get_matches_from(vec!["cogni", "--schema", "does_not_exist.json"]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn chat_tools_file() -> Result<()> {
+        let file = assert_fs::NamedTempFile::new("tools.json")?;
+        file.write_str(r#"[{"name": "get_time"}]"#)?;
+
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "--tools", file.path().to_str().unwrap()])
+            .map(Invocation::from)?;
+
+        assert_eq!(
+            args.tools
+                .iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["get_time"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn chat_tools_file_invalid() -> Result<()> {
+        let file = assert_fs::NamedTempFile::new("tools.json")?;
+        file.write_str(r#"[{"description": "missing name"}]"#)?;
+
+        let res =
+            cli().try_get_matches_from(vec!["cogni", "--tools", file.path().to_str().unwrap()]);
+        assert!(res.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn chat_template_with_vars() -> Result<()> {
+        let file = assert_fs::NamedTempFile::new("commit-msg.tmpl")?;
+        file.write_str("---\nmodel = \"gpt-5-mini\"\n---\nWrite in {{style}} style:\n{{input}}")?;
+
+        let args = cli()
+            .try_get_matches_from(vec![
+                "cogni",
+                "-f",
+                file.path().to_str().unwrap(),
+                "--var",
+                "style=conventional",
+                "--var",
+                "input=a=b",
+            ])
+            .map(Invocation::from)?;
+
+        let template = args.template.expect("template should be set");
+        assert_eq!(template.settings.model.as_deref(), Some("gpt-5-mini"));
+        assert_eq!(
+            args.vars,
+            BTreeMap::from([
+                ("input".to_string(), "a=b".to_string()),
+                ("style".to_string(), "conventional".to_string()),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn chat_template_missing() {
+        let res = cli().try_get_matches_from(vec!["cogni", "-f", "does_not_exist.tmpl"]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn chat_var_invalid() {
+        let res = cli().try_get_matches_from(vec!["cogni", "--var", "style"]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn chat_session_flag() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "--session", "work", "-u", "USER"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.session.as_deref(), Some("work"));
+        assert_eq!(args.mode, Mode::Chat);
+        Ok(())
+    }
+
+    #[test]
+    fn session_commands() -> Result<()> {
+        for (argv, cmd) in [
+            (vec!["cogni", "session", "list"], SessionCommand::List),
+            (
+                vec!["cogni", "session", "show", "work"],
+                SessionCommand::Show("work".to_string()),
+            ),
+            (
+                vec!["cogni", "session", "rm", "work"],
+                SessionCommand::Remove("work".to_string()),
+            ),
+            (
+                vec!["cogni", "session", "fork", "work", "work-2"],
+                SessionCommand::Fork {
+                    from: "work".to_string(),
+                    to: "work-2".to_string(),
+                },
+            ),
+        ] {
+            let args = cli().try_get_matches_from(argv).map(Invocation::from)?;
+            assert_eq!(args.mode, Mode::Session(cmd));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cache_commands() -> Result<()> {
+        for (argv, cmd) in [
+            (vec!["cogni", "cache", "stats"], CacheCommand::Stats),
+            (vec!["cogni", "cache", "clear"], CacheCommand::Clear),
+        ] {
+            let args = cli().try_get_matches_from(argv).map(Invocation::from)?;
+            assert_eq!(args.mode, Mode::Cache(cmd));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn chat_cache_flags() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-u", "USER"])
+            .map(Invocation::from)?;
+        assert!(!args.cache);
+        assert_eq!(args.cache_ttl, Duration::from_secs(604800));
+
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "--cache", "--cache-ttl", "60", "-u", "USER"])
+            .map(Invocation::from)?;
+        assert!(args.cache);
+        assert_eq!(args.cache_ttl, Duration::from_secs(60));
+
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "--cache", "--no-cache", "-u", "USER"])
+            .map(Invocation::from)?;
+        assert!(!args.cache);
+        Ok(())
+    }
+
+    #[test]
+    fn chat_cache_config_overridden() -> Result<()> {
+        let settings = Settings {
+            cache: Some(true),
+            cache_ttl: Some(60),
+            ..Settings::default()
+        };
+
+        let matches = cli().try_get_matches_from(vec!["cogni", "-u", "USER"])?;
+        let args = Invocation::from_matches(matches, &settings);
+        assert!(args.cache);
+        assert_eq!(args.cache_ttl, Duration::from_secs(60));
+
+        let matches = cli().try_get_matches_from(vec!["cogni", "--no-cache", "-u", "USER"])?;
+        let args = Invocation::from_matches(matches, &settings);
+        assert!(!args.cache);
+        Ok(())
+    }
+
+    #[test]
+    fn chat_continue_flags() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec![
+                "cogni",
+                "--continue",
+                "resp_123",
+                "--store",
+                "--print-id",
+                "--id-file",
+                "last_id",
+                "-u",
+                "USER",
+            ])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.previous_response_id.as_deref(), Some("resp_123"));
+        assert_eq!(args.store, Some(true));
+        assert!(args.print_id);
+        assert_eq!(args.id_file.as_deref(), Some("last_id"));
+        Ok(())
+    }
+
+    #[test]
+    fn chat_continue_flags_default() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-u", "USER"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.previous_response_id, None);
+        assert_eq!(args.store, None);
+        assert!(!args.print_id);
+        assert_eq!(args.id_file, None);
+        Ok(())
+    }
+
+    #[test]
+    fn chat_mode_default() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "-u", "USER"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.mode, Mode::Chat);
+        Ok(())
+    }
+
+    #[test]
+    fn batch_args() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec![
+                "cogni",
+                "batch",
+                "-s",
+                "SYSTEM",
+                "-j",
+                "8",
+                "--records",
+                "ndjson",
+                "--unordered",
+                "records.ndjson",
+            ])
+            .map(Invocation::from)?;
+
+        assert_eq!(
+            args.mode,
+            Mode::Batch(BatchArgs {
+                concurrency: 8,
+                ordered: false,
+                record_format: RecordFormat::NDJSON,
+            })
+        );
+        assert_eq!(args.messages, vec![Message::system("SYSTEM")]);
+        assert_eq!(args.file, "records.ndjson");
+        Ok(())
+    }
+
+    #[test]
+    fn batch_args_default() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "batch"])
+            .map(Invocation::from)?;
+
+        assert_eq!(
+            args.mode,
+            Mode::Batch(BatchArgs {
+                concurrency: 4,
+                ordered: true,
+                record_format: RecordFormat::Lines,
+            })
+        );
+        assert_eq!(args.file, "-");
+        Ok(())
+    }
+
+    #[test]
+    fn batch_concurrency_must_be_positive() {
+        let res = cli().try_get_matches_from(vec!["cogni", "batch", "-j", "0"]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn agent_args() -> Result<()> {
+        let config = assert_fs::NamedTempFile::new("agent.json")?;
+        config.write_str(r#"[{"name": "ls", "command": "ls {{path}}"}]"#)?;
+
+        let args = cli()
+            .try_get_matches_from(vec![
+                "cogni",
+                "agent",
+                "--config",
+                config.path().to_str().unwrap(),
+                "--max-steps",
+                "3",
+                "--yes",
+                "-u",
+                "List files",
+            ])
+            .map(Invocation::from)?;
+
+        let Mode::Agent(agent) = &args.mode else {
+            panic!("expected agent mode, got {:?}", args.mode);
+        };
+        assert_eq!(agent.max_steps, 3);
+        assert!(!agent.confirm);
+        assert_eq!(agent.tools[0].command, "ls {{path}}");
+        assert_eq!(args.tools, vec![agent.tools[0].tool.clone()]);
+        assert_eq!(args.messages, vec![Message::user("List files")]);
+        Ok(())
+    }
+
+    #[test]
+    fn agent_requires_config() {
+        let res = cli().try_get_matches_from(vec!["cogni", "agent", "-u", "USER"]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn chat_settings_from_config() -> Result<()> {
+        let settings = Settings {
+            provider: Some(ProviderKind::ChatCompletions),
+            model: Some("qwen".to_string()),
+            endpoint: Some("http://localhost:8080".to_string()),
+            api_key_cmd: Some("echo KEY".to_string()),
+            temperature: Some(0.2),
+            timeout: Some(120),
+            reasoning_effort: Some(ReasoningEffort::Low),
+            system: Some("SYSTEM".to_string()),
+            ..Settings::default()
+        };
+        let matches = cli().try_get_matches_from(vec!["cogni", "-u", "USER"])?;
+        let args = Invocation::from_matches(matches, &settings);
+
+        assert_eq!(args.provider, ProviderKind::ChatCompletions);
+        assert_eq!(args.model, "qwen");
+        assert_eq!(args.endpoint.as_deref(), Some("http://localhost:8080"));
+        assert_eq!(args.api_key_cmd.as_deref(), Some("echo KEY"));
+        assert_eq!(args.temperature, Some(0.2));
+        assert_eq!(args.timeout, Duration::from_secs(120));
+        assert_eq!(args.reasoning_effort, Some(ReasoningEffort::Low));
+        assert_eq!(
+            args.messages,
+            vec![Message::system("SYSTEM"), Message::user("USER")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn chat_flags_override_config() -> Result<()> {
+        let settings = Settings {
+            provider: Some(ProviderKind::Ollama),
+            model: Some("llama3".to_string()),
+            timeout: Some(120),
+            system: Some("CONFIG".to_string()),
+            ..Settings::default()
+        };
+        let matches = cli().try_get_matches_from(vec![
+            "cogni",
+            "-m",
+            "anthropic:claude-sonnet-4-5",
+            "-T",
+            "5",
+            "-s",
+            "FLAG",
+        ])?;
+        let args = Invocation::from_matches(matches, &settings);
+
+        assert_eq!(args.provider, ProviderKind::Anthropic);
+        assert_eq!(args.model, "claude-sonnet-4-5");
+        assert_eq!(args.timeout, Duration::from_secs(5));
+        assert_eq!(args.messages, vec![Message::system("FLAG")]);
+        Ok(())
+    }
+
+    #[test]
+    fn config_show() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "config", "show", "-m", "gpt-5"])
+            .map(Invocation::from)?;
+
+        assert_eq!(args.mode, Mode::ShowConfig);
+        assert_eq!(args.model, "gpt-5");
+        Ok(())
+    }
+
+    #[test]
+    fn usage_report_since() -> Result<()> {
+        let args = cli()
+            .try_get_matches_from(vec!["cogni", "usage", "report", "--since", "7d"])
+            .map(Invocation::from)?;
+        assert_eq!(
+            args.mode,
+            Mode::Usage(UsageCommand::Report {
+                since: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+                group_by: GroupBy::Model,
+            })
+        );
+
+        for since in ["7", "7y", "999999999999w", "99999999999999w"] {
+            assert!(
+                cli()
+                    .try_get_matches_from(vec!["cogni", "usage", "report", "--since", since])
+                    .is_err(),
+                "--since {since} should be rejected"
+            );
+        }
+        Ok(())
+    }
 }
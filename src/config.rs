@@ -0,0 +1,306 @@
+//! Configuration file with defaults and named profiles
+//!
+//! ```toml
+//! [defaults]
+//! model = "gpt-5.5"
+//! timeout = 120
+//!
+//! [profiles.summarizer]
+//! model = "gpt-5-mini"
+//! system = "Summarize the input in 3 bullet points"
+//!
+//! [prices."gpt-5-mini"]
+//! input = 0.25
+//! cached_input = 0.025
+//! output = 2.0
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+use crate::openai::{ReasoningEffort, Usage};
+use crate::provider::ProviderKind;
+
+/// Settings that config defaults and profiles may supply
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub provider: Option<ProviderKind>,
+    pub model: Option<String>,
+    /// Base URL for provider's API, used unless the provider's endpoint environment variable is set
+    pub endpoint: Option<String>,
+    /// Path of rules file that `mock` provider replies from
+    pub mock_rules: Option<String>,
+    /// Shell command printing API key, used unless key is given via flag or environment
+    pub api_key_cmd: Option<String>,
+    pub temperature: Option<f32>,
+    /// Timeout in seconds
+    pub timeout: Option<u64>,
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// System prompt, used unless `--system` is given
+    pub system: Option<String>,
+    /// Whether responses are cached, unless `--cache` or `--no-cache` is given
+    pub cache: Option<bool>,
+    /// Seconds that cached responses are used for
+    pub cache_ttl: Option<u64>,
+    /// Estimated spend in USD per local day, after which requests are refused
+    pub daily_budget: Option<f64>,
+    /// Estimated spend in USD per local month, after which requests are refused
+    pub monthly_budget: Option<f64>,
+}
+
+/// Contents of configuration file
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub defaults: Settings,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Settings>,
+    /// Prices by model, for estimating cost of requests
+    #[serde(default)]
+    pub prices: BTreeMap<String, Price>,
+}
+
+/// Price of model in USD per million tokens
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Price {
+    pub input: f64,
+    /// Price of input tokens served from prompt cache. Same as `input` if `None`
+    pub cached_input: Option<f64>,
+    pub output: f64,
+}
+
+impl Price {
+    /// Estimated cost of `usage` in USD
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let cached = usage.cached_tokens.min(usage.input_tokens);
+        let uncached = usage.input_tokens - cached;
+        let per_token = |price: f64| price / 1_000_000.0;
+
+        uncached as f64 * per_token(self.input)
+            + cached as f64 * per_token(self.cached_input.unwrap_or(self.input))
+            + usage.output_tokens as f64 * per_token(self.output)
+    }
+}
+
+/// Configuration directory, `cogni` under `$XDG_CONFIG_HOME` or `~/.config`
+pub fn dir() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("cogni"))
+}
+
+/// Path to configuration file. `$COGNI_CONFIG` if set, otherwise `config.toml` in `dir()`
+pub fn path() -> Option<PathBuf> {
+    match std::env::var_os("COGNI_CONFIG") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => dir().map(|dir| dir.join("config.toml")),
+    }
+}
+
+impl Config {
+    /// Load configuration file at `path()`. Missing files are treated as empty configuration
+    pub fn load() -> Result<Self, Error> {
+        let Some(path) = path() else {
+            return Ok(Self::default());
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| Error::InvalidConfig(format!("{} - {e}", path.display()))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::IO(e)),
+        }
+    }
+
+    /// Parse configuration from TOML
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        toml::from_str(content).map_err(|e| Error::InvalidConfig(e.message().to_string()))
+    }
+
+    /// Settings for `profile` layered over defaults
+    pub fn resolve(&self, profile: Option<&str>) -> Result<Settings, Error> {
+        match profile {
+            None => Ok(self.defaults.clone()),
+            Some(name) => match self.profiles.get(name) {
+                Some(settings) => Ok(self.defaults.clone().merge(settings.clone())),
+                None => Err(Error::InvalidConfig(format!("unknown profile \"{name}\""))),
+            },
+        }
+    }
+}
+
+impl Settings {
+    /// Settings with values in `other` taking precedence
+    pub fn merge(self, other: Settings) -> Settings {
+        Settings {
+            provider: other.provider.or(self.provider),
+            model: other.model.or(self.model),
+            endpoint: other.endpoint.or(self.endpoint),
+            mock_rules: other.mock_rules.or(self.mock_rules),
+            api_key_cmd: other.api_key_cmd.or(self.api_key_cmd),
+            temperature: other.temperature.or(self.temperature),
+            timeout: other.timeout.or(self.timeout),
+            reasoning_effort: other.reasoning_effort.or(self.reasoning_effort),
+            system: other.system.or(self.system),
+            cache: other.cache.or(self.cache),
+            cache_ttl: other.cache_ttl.or(self.cache_ttl),
+            daily_budget: other.daily_budget.or(self.daily_budget),
+            monthly_budget: other.monthly_budget.or(self.monthly_budget),
+        }
+    }
+}
+
+/// Run `api_key_cmd` via `sh`, returning first line of its output as API key
+pub fn run_key_command(cmd: &str) -> Result<String, Error> {
+    let output = Command::new("sh").arg("-c").arg(cmd).output()?;
+
+    if !output.status.success() {
+        return Err(Error::APIKeyCommand(format!(
+            "{} - {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.lines().next().map(str::trim) {
+        Some(key) if !key.is_empty() => Ok(key.to_string()),
+        _ => Err(Error::APIKeyCommand("command printed no key".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[defaults]
+model = "gpt-5"
+timeout = 120
+api_key_cmd = "echo KEY"
+cache = true
+
+[profiles.summarizer]
+model = "gpt-5-mini"
+temperature = 0.2
+system = "Summarize"
+
+[profiles.local]
+provider = "ollama"
+model = "llama3"
+endpoint = "http://gpu-box:11434"
+reasoning_effort = "low"
+
+[prices."gpt-5"]
+input = 1.25
+cached_input = 0.125
+output = 10.0
+"#;
+
+    #[test]
+    fn resolve_defaults() {
+        let config = Config::parse(CONFIG).expect("should parse");
+        assert_eq!(
+            config.resolve(None).unwrap(),
+            Settings {
+                model: Some("gpt-5".to_string()),
+                timeout: Some(120),
+                api_key_cmd: Some("echo KEY".to_string()),
+                cache: Some(true),
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_profile_over_defaults() {
+        let config = Config::parse(CONFIG).expect("should parse");
+        assert_eq!(
+            config.resolve(Some("summarizer")).unwrap(),
+            Settings {
+                model: Some("gpt-5-mini".to_string()),
+                timeout: Some(120),
+                api_key_cmd: Some("echo KEY".to_string()),
+                cache: Some(true),
+                temperature: Some(0.2),
+                system: Some("Summarize".to_string()),
+                ..Settings::default()
+            }
+        );
+
+        let local = config.resolve(Some("local")).unwrap();
+        assert_eq!(local.provider, Some(ProviderKind::Ollama));
+        assert_eq!(local.reasoning_effort, Some(ReasoningEffort::Low));
+    }
+
+    #[test]
+    fn resolve_unknown_profile() {
+        let config = Config::parse(CONFIG).expect("should parse");
+        let err = config.resolve(Some("missing")).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidConfig(reason) if reason.contains("missing")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_unknown_field() {
+        let err = Config::parse("[defaults]\nmodle = \"gpt-5\"\n").expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidConfig(reason) if reason.contains("modle")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_unknown_provider() {
+        let err = Config::parse("[defaults]\nprovider = \"acme\"\n").expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidConfig(reason) if reason.contains("acme")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn price_cost() {
+        let config = Config::parse(CONFIG).expect("should parse");
+        let price = &config.prices["gpt-5"];
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            total_tokens: 1_100_000,
+            cached_tokens: 200_000,
+            reasoning_tokens: 50_000,
+        };
+        // 800k uncached and 200k cached input tokens, and 100k output tokens
+        assert!((price.cost(&usage) - (1.0 + 0.025 + 1.0)).abs() < 1e-9);
+
+        let uncached = Price {
+            cached_input: None,
+            ..price.clone()
+        };
+        assert!((uncached.cost(&usage) - (1.25 + 1.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn key_command() {
+        assert_eq!(run_key_command("printf 'KEY\\nother'").unwrap(), "KEY");
+        assert!(matches!(
+            run_key_command("exit 1"),
+            Err(Error::APIKeyCommand(_))
+        ));
+        assert!(matches!(
+            run_key_command("true"),
+            Err(Error::APIKeyCommand(_))
+        ));
+    }
+}
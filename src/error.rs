@@ -1,12 +1,49 @@
 //! Errors for cogni library crate
 
+use serde::Serialize;
+
+use crate::openai::APIError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("no API key provided")]
     NoAPIKey,
 
     #[error("failed to fetch - {0}")]
-    FailedToFetch(#[from] reqwest::Error),
+    FailedToFetch(reqwest::Error),
+
+    #[error("request timed out - {0}")]
+    Timeout(reqwest::Error),
+
+    #[error("invalid config - {0}")]
+    InvalidConfig(String),
+
+    #[error("api key command failed - {0}")]
+    APIKeyCommand(String),
+
+    #[error("invalid template - {0}")]
+    InvalidTemplate(String),
+
+    #[error("missing template variables: {}", .0.join(", "))]
+    MissingTemplateVariables(Vec<String>),
+
+    #[error("invalid attachment - {0}")]
+    InvalidAttachment(String),
+
+    #[error("invalid session - {0}")]
+    InvalidSession(String),
+
+    #[error("invalid cache - {0}")]
+    InvalidCache(String),
+
+    #[error("invalid cassette - {0}")]
+    InvalidCassette(String),
+
+    #[error("no recording in {0} matches request")]
+    NoRecording(String),
+
+    #[error("invalid mock rules - {0}")]
+    InvalidMockRules(String),
 
     #[error("no messages provided")]
     NoMessagesProvided,
@@ -14,12 +51,265 @@ pub enum Error {
     #[error("unexpected response - {0}")]
     UnexpectedResponse(String),
 
+    #[error("response is incomplete - {0}")]
+    IncompleteResponse(String),
+
+    #[error("invalid transcript at line {line} - {reason}")]
+    InvalidTranscript { line: usize, reason: String },
+
+    #[error("invalid message at record {index} - {reason}")]
+    InvalidMessageRecord { index: usize, reason: String },
+
+    #[error("invalid json schema - {0}")]
+    InvalidSchema(String),
+
+    #[error("invalid tool at index {index} - {reason}")]
+    InvalidTool { index: usize, reason: String },
+
+    #[error("output does not match schema at {path} - {message}")]
+    SchemaValidation { path: String, message: String },
+
     #[error("io error - {0}")]
     IO(#[from] std::io::Error),
 
     #[error("json serialization error - {0}")]
     JSON(#[from] serde_json::Error),
 
+    #[error("{failed} of {total} records failed")]
+    BatchFailed { failed: usize, total: usize },
+
+    #[error("agent did not finish within {steps} steps")]
+    AgentStepsExhausted { steps: usize },
+
+    #[error("budget exceeded - {0}")]
+    BudgetExceeded(String),
+
+    #[error("gave up after {attempts} attempts - {source}")]
+    RetriesExhausted { attempts: u32, source: Box<Error> },
+
+    #[error("rate limited - {}", .error.message)]
+    RateLimited { error: APIError },
+
     #[error("openai api returned error - {}", .error.message)]
-    OpenAIError { error: crate::openai::APIError },
+    OpenAIError { error: APIError },
+}
+
+/// Classes of errors, each with a stable process exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Errors not covered by other kinds, e.g. I/O errors
+    Other,
+    /// Invalid flags, config, templates, sessions, or input
+    Usage,
+    /// Response cut short by output token limit or content filter
+    Incomplete,
+    /// Missing or rejected API key
+    Auth,
+    /// Request rate limited by API
+    RateLimit,
+    /// Request timed out
+    Timeout,
+    /// API returned an error or unexpected response
+    Api,
+    /// Request failed to reach API
+    Network,
+    /// Output does not match requested schema
+    SchemaValidation,
+    /// Some records of batch failed
+    BatchFailed,
+    /// Agent did not finish within step limit
+    AgentStepsExhausted,
+    /// Request refused since estimated spend reached budget
+    Budget,
+}
+
+impl ErrorKind {
+    /// Process exit status. 0 is success, and 2 is also used for invalid flags rejected by argument
+    /// parsing
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::Incomplete => 3,
+            ErrorKind::Auth => 4,
+            ErrorKind::RateLimit => 5,
+            ErrorKind::Timeout => 6,
+            ErrorKind::Api => 7,
+            ErrorKind::Network => 8,
+            ErrorKind::SchemaValidation => 9,
+            ErrorKind::BatchFailed => 10,
+            ErrorKind::AgentStepsExhausted => 11,
+            ErrorKind::Budget => 12,
+        }
+    }
+}
+
+impl Error {
+    /// Error for API error response with HTTP `status`
+    pub(crate) fn api(status: u16, error: APIError) -> Self {
+        let error = APIError {
+            status: Some(status),
+            ..error
+        };
+        match status {
+            429 => Error::RateLimited { error },
+            _ => Error::OpenAIError { error },
+        }
+    }
+
+    /// Class of error. Errors after retries are classified by the error of the last attempt
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::NoAPIKey | Error::APIKeyCommand(_) => ErrorKind::Auth,
+            Error::FailedToFetch(_) => ErrorKind::Network,
+            Error::Timeout(_) => ErrorKind::Timeout,
+            Error::InvalidConfig(_)
+            | Error::InvalidTemplate(_)
+            | Error::MissingTemplateVariables(_)
+            | Error::InvalidAttachment(_)
+            | Error::InvalidSession(_)
+            | Error::NoMessagesProvided
+            | Error::InvalidTranscript { .. }
+            | Error::InvalidMessageRecord { .. }
+            | Error::InvalidSchema(_)
+            | Error::InvalidTool { .. }
+            | Error::InvalidCassette(_)
+            | Error::InvalidMockRules(_) => ErrorKind::Usage,
+            Error::UnexpectedResponse(_) => ErrorKind::Api,
+            Error::IncompleteResponse(_) => ErrorKind::Incomplete,
+            Error::SchemaValidation { .. } => ErrorKind::SchemaValidation,
+            Error::IO(_) | Error::JSON(_) | Error::InvalidCache(_) | Error::NoRecording(_) => {
+                ErrorKind::Other
+            }
+            Error::BatchFailed { .. } => ErrorKind::BatchFailed,
+            Error::AgentStepsExhausted { .. } => ErrorKind::AgentStepsExhausted,
+            Error::BudgetExceeded(_) => ErrorKind::Budget,
+            Error::RetriesExhausted { source, .. } => source.kind(),
+            Error::RateLimited { .. } => ErrorKind::RateLimit,
+            Error::OpenAIError { error } => match error.status {
+                Some(401 | 403) => ErrorKind::Auth,
+                _ => ErrorKind::Api,
+            },
+        }
+    }
+
+    /// Process exit status for error
+    pub fn exit_code(&self) -> u8 {
+        self.kind().exit_code()
+    }
+
+    /// Error returned by API, if any
+    pub fn api_error(&self) -> Option<&APIError> {
+        match self {
+            Error::RateLimited { error } | Error::OpenAIError { error } => Some(error),
+            Error::RetriesExhausted { source, .. } => source.api_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Error::Timeout(e)
+        } else {
+            Error::FailedToFetch(e)
+        }
+    }
+}
+
+/// Machine-readable description of an error, printed for `--error-format json`
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    /// Error message, including its causes
+    pub message: String,
+    /// HTTP status of API error
+    pub status: Option<u16>,
+    pub code: Option<String>,
+    pub param: Option<String>,
+}
+
+impl ErrorReport {
+    /// Report for `err`, classified by the first `Error` in its chain of causes
+    pub fn new(err: &anyhow::Error) -> Self {
+        let error = err.chain().find_map(|e| e.downcast_ref::<Error>());
+        let api_error = error.and_then(Error::api_error);
+        Self {
+            kind: error.map_or(ErrorKind::Other, Error::kind),
+            message: format!("{err:#}"),
+            status: api_error.and_then(|e| e.status),
+            code: api_error.and_then(|e| e.code.clone()),
+            param: api_error.and_then(|e| e.param.clone()),
+        }
+    }
+
+    /// Process exit status for error
+    pub fn exit_code(&self) -> u8 {
+        self.kind.exit_code()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn api_error(code: Option<&str>) -> APIError {
+        APIError {
+            message: "Failed".to_string(),
+            error_type: "invalid_request_error".to_string(),
+            param: Some("model".to_string()),
+            code: code.map(str::to_string),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn api_error_kind_by_status() {
+        assert_eq!(
+            Error::api(429, api_error(None)).kind(),
+            ErrorKind::RateLimit
+        );
+        assert_eq!(Error::api(401, api_error(None)).kind(), ErrorKind::Auth);
+        assert_eq!(Error::api(400, api_error(None)).kind(), ErrorKind::Api);
+    }
+
+    #[test]
+    fn retries_exhausted_kind_of_last_attempt() {
+        let err = Error::RetriesExhausted {
+            attempts: 3,
+            source: Box::new(Error::api(429, api_error(None))),
+        };
+        assert_eq!(err.kind(), ErrorKind::RateLimit);
+        assert_eq!(err.exit_code(), 5);
+        assert_eq!(err.api_error().and_then(|e| e.status), Some(429));
+    }
+
+    #[test]
+    fn report_for_api_error() {
+        let err = anyhow::Error::from(Error::api(404, api_error(Some("model_not_found"))))
+            .context("failed to fetch request");
+
+        let report = ErrorReport::new(&err);
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "kind": "api",
+                "message": "failed to fetch request: openai api returned error - Failed",
+                "status": 404,
+                "code": "model_not_found",
+                "param": "model",
+            })
+        );
+        assert_eq!(report.exit_code(), 7);
+    }
+
+    #[test]
+    fn report_for_other_error() {
+        let report = ErrorReport::new(&anyhow::anyhow!("something went wrong"));
+        assert_eq!(report.kind, ErrorKind::Other);
+        assert_eq!(report.exit_code(), 1);
+        assert_eq!(report.status, None);
+    }
 }
@@ -0,0 +1,172 @@
+//! Implements agent subcommand
+
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Output, Stdio};
+
+use anyhow::{Context, Result};
+
+use crate::Error;
+use crate::cli::{AgentArgs, Invocation};
+use crate::exec::chat;
+use crate::openai::{Message, ToolCall, Usage};
+use crate::tools::CommandTool;
+
+/// Executes `Invocation` in a loop, running tool calls as commands and sending their output back
+/// until model replies without tool calls, or `agent.max_steps` requests are made
+pub async fn exec(args: Invocation, agent: AgentArgs) -> Result<()> {
+    let provider = chat::create_provider(&args)?;
+
+    let mut msgs = chat::input_messages(&args)?;
+
+    if msgs.is_empty() {
+        return Err(Error::NoMessagesProvided.into());
+    }
+
+    let mut usage = Usage::default();
+    for step in 1..=agent.max_steps {
+        let request = chat::build_request(&args, msgs.clone())?;
+        let res = provider
+            .create_response(&request)
+            .await
+            .with_context(|| "failed to fetch request")?;
+        usage += &res.usage;
+
+        // Tool calls of truncated responses may have partial arguments
+        chat::check_complete(&res)?;
+        let choice = chat::single_choice(&res)?;
+        if choice.message.tool_calls.is_empty() {
+            chat::validate_response(&args, &res)?;
+            chat::show_response(io::stdout(), &args, args.id.as_deref(), &msgs, &res)?;
+            if args.usage {
+                chat::show_usage("total usage", &usage, args.price.as_ref());
+            }
+            return Ok(());
+        }
+
+        msgs.push(choice.message.clone());
+        for call in &choice.message.tool_calls {
+            eprintln!("[step {step}] {} {}", call.name, call.arguments);
+            let output = run_tool_call(&agent, call).await?;
+            msgs.push(Message::tool(&call.call_id, &output));
+        }
+    }
+
+    if args.usage {
+        chat::show_usage("total usage", &usage, args.price.as_ref());
+    }
+    Err(Error::AgentStepsExhausted {
+        steps: agent.max_steps,
+    }
+    .into())
+}
+
+/// Run tool call, returning output to send back to model
+async fn run_tool_call(agent: &AgentArgs, call: &ToolCall) -> Result<String> {
+    let Some(tool) = agent.tools.iter().find(|t| t.tool.name == call.name) else {
+        eprintln!("unknown tool \"{}\"", call.name);
+        return Ok(format!("error: unknown tool \"{}\"", call.name));
+    };
+
+    let command = match tool.render(&call.arguments) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("invalid arguments - {e}");
+            return Ok(format!("error: invalid arguments - {e}"));
+        }
+    };
+
+    if agent.confirm && !confirm(&command)? {
+        eprintln!("skipped {command}");
+        return Ok("error: user declined to run command".to_string());
+    }
+
+    eprintln!("+ {command}");
+    run_command(tool, &command).await
+}
+
+/// Prompt on terminal whether `command` should run
+fn confirm(command: &str) -> Result<bool> {
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .with_context(|| "failed to open terminal to confirm tool call, see --yes")?;
+
+    write!(tty, "Run `{command}`? [y/N] ")?;
+    tty.flush()?;
+
+    let mut answer = String::new();
+    BufReader::new(tty).read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Run `command` via `sh`, returning stdout. On failure, stdout, stderr, and exit status are
+/// returned as labeled sections
+async fn run_command(tool: &CommandTool, command: &str) -> Result<String> {
+    let output = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("failed to run tool {}", tool.tool.name))?;
+
+    if !output.status.success() {
+        eprintln!("{} {}", tool.tool.name, output.status);
+    }
+    Ok(format_output(&output))
+}
+
+/// Tool output sent back to model for command's `output`
+fn format_output(output: &Output) -> String {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() {
+        return stdout.into_owned();
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let section = |label: &str, text: &str| match text.ends_with('\n') || text.is_empty() {
+        true => format!("{label}:\n{text}"),
+        false => format!("{label}:\n{text}\n"),
+    };
+    format!(
+        "{}{}{}",
+        section("stdout", &stdout),
+        section("stderr", &stderr),
+        output.status
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    use super::*;
+
+    fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn format_output_of_success() {
+        assert_eq!(format_output(&output(0, "done\n", "warning\n")), "done\n");
+    }
+
+    #[test]
+    fn format_output_of_failure() {
+        assert_eq!(
+            format_output(&output(2, "partial", "no such file\n")),
+            "stdout:\npartial\nstderr:\nno such file\nexit status: 2"
+        );
+        assert_eq!(
+            format_output(&output(1, "", "")),
+            "stdout:\nstderr:\nexit status: 1"
+        );
+    }
+}
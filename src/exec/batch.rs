@@ -0,0 +1,378 @@
+//! Implements batch subcommand
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::Error;
+use crate::attachment::LocalFiles;
+use crate::cli::{BatchArgs, Invocation, OnIncomplete, OutputFormat, RecordFormat};
+use crate::exec::chat::{self, NDJSONRecord};
+use crate::openai::{Message, Response, Usage};
+use crate::parse;
+use crate::provider::Provider;
+
+/// Record read from batch input
+#[derive(Debug)]
+struct Record {
+    id: String,
+    messages: Result<Vec<Message>, Error>,
+}
+
+/// Result of running a single record
+struct Outcome {
+    index: usize,
+    id: String,
+    result: Result<(Vec<Message>, Response)>,
+}
+
+/// How a record's outcome was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Written {
+    /// Response was printed
+    Complete,
+    /// Response cut short was printed per `OnIncomplete::Print`
+    Incomplete,
+    /// Failure was reported
+    Failed,
+}
+
+/// Shape of records in `RecordFormat::NDJSON`
+#[derive(Debug, Deserialize)]
+struct NDJSONInput {
+    id: Option<Value>,
+    input: Option<String>,
+    messages: Option<Vec<Message>>,
+}
+
+/// Executes `Invocation` once per record, with at most `batch.concurrency` requests in flight
+pub async fn exec(args: Invocation, batch: BatchArgs) -> Result<()> {
+    let provider: Arc<dyn Provider> = Arc::from(chat::create_provider(&args)?);
+
+    let content =
+        read_input(&args.file).with_context(|| format!("failed to open {}", &args.file))?;
+    // Records are often generated, so they only attach local files if explicitly allowed
+    let local = chat::local_files(&args.file, args.allow_local_attachments);
+    let records = parse_records(&content, batch.record_format, &local);
+    let total = records.len();
+
+    if total == 0 {
+        return Err(Error::NoMessagesProvided.into());
+    }
+
+    let args = Arc::new(args);
+    let semaphore = Arc::new(Semaphore::new(batch.concurrency));
+    let mut tasks = JoinSet::new();
+
+    for (index, record) in records.into_iter().enumerate() {
+        let provider = provider.clone();
+        let args = args.clone();
+        let semaphore = semaphore.clone();
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result = match record.messages {
+                Ok(msgs) => run_record(provider.as_ref(), &args, msgs).await,
+                Err(e) => Err(e.into()),
+            };
+            Outcome {
+                index,
+                id: record.id,
+                result,
+            }
+        });
+    }
+
+    let mut failed = 0;
+    let mut incomplete = 0;
+    let mut pending = BTreeMap::new();
+    let mut next_index = 0;
+    let mut usage = Usage::default();
+    let mut budget_exceeded = None;
+
+    while let Some(joined) = tasks.join_next().await {
+        let outcome = joined.with_context(|| "batch task failed")?;
+        match &outcome.result {
+            Ok((_, res)) => usage += &res.usage,
+            Err(e) => {
+                if let Some(Error::BudgetExceeded(reason)) =
+                    e.chain().find_map(|e| e.downcast_ref::<Error>())
+                {
+                    budget_exceeded = Some(reason.clone());
+                }
+            }
+        }
+
+        let mut written = Vec::new();
+        if batch.ordered {
+            pending.insert(outcome.index, outcome);
+            while let Some(outcome) = pending.remove(&next_index) {
+                written.push(write_outcome(&args, true, outcome)?);
+                next_index += 1;
+            }
+        } else {
+            written.push(write_outcome(&args, false, outcome)?);
+        }
+
+        for w in written {
+            match w {
+                Written::Complete => {}
+                Written::Incomplete => incomplete += 1,
+                Written::Failed => failed += 1,
+            }
+        }
+    }
+
+    if args.usage {
+        chat::show_usage("total usage", &usage, args.price.as_ref());
+    }
+
+    // Records refused by budget are reported as such, rather than as failed records
+    if let Some(reason) = budget_exceeded {
+        return Err(Error::BudgetExceeded(reason).into());
+    }
+
+    if failed != 0 {
+        return Err(Error::BatchFailed { failed, total }.into());
+    }
+
+    if incomplete != 0 {
+        return Err(Error::IncompleteResponse(format!(
+            "{incomplete} of {total} records cut short"
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Send request for a single record
+async fn run_record(
+    provider: &dyn Provider,
+    args: &Invocation,
+    msgs: Vec<Message>,
+) -> Result<(Vec<Message>, Response)> {
+    let mut msgs = [args.messages.clone(), msgs].concat();
+    chat::attach(&mut msgs, &args.attachments);
+    let request = chat::build_request(args, msgs.clone())?;
+    let res = provider
+        .create_response(&request)
+        .await
+        .with_context(|| "failed to fetch request")?;
+    if args.on_incomplete == OnIncomplete::Fail {
+        chat::check_complete(&res)?;
+    }
+    chat::validate_response(args, &res)?;
+    Ok((msgs, res))
+}
+
+/// Write outcome of record to stdout, or report failure on stderr
+fn write_outcome(args: &Invocation, ordered: bool, outcome: Outcome) -> Result<Written> {
+    let mut stdout = io::stdout();
+    let id = outcome.id.as_str();
+
+    let err = match outcome.result {
+        Ok((msgs, res)) => {
+            if !ordered && args.output_format == OutputFormat::Plaintext {
+                write!(stdout, "{id}\t")?;
+            }
+            match chat::show_response(&mut stdout, args, Some(id), &msgs, &res) {
+                Ok(()) => match chat::check_complete(&res) {
+                    Ok(()) => return Ok(Written::Complete),
+                    Err(e) => {
+                        eprintln!("record {id} incomplete - {e}");
+                        return Ok(Written::Incomplete);
+                    }
+                },
+                Err(e) => anyhow::Error::from(e),
+            }
+        }
+        Err(e) => e,
+    };
+
+    eprintln!("record {id} failed - {err:#}");
+
+    if args.output_format == OutputFormat::NDJSON {
+        let record = NDJSONRecord::Error {
+            id: Some(id),
+            error: format!("{err:#}"),
+        };
+        serde_json::to_writer(&mut stdout, &record)?;
+        writeln!(stdout)?;
+    }
+
+    Ok(Written::Failed)
+}
+
+/// Read entire input from stdin or file
+fn read_input(file: &str) -> Result<String> {
+    let mut reader: Box<dyn Read> = match file {
+        "-" => Box::new(io::stdin()),
+        file => Box::new(File::open(file)?),
+    };
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Parse records from input, skipping blank lines. Records are identified by their index unless
+/// NDJSON records specify an `id`. Attachments of local files are loaded per `local`
+fn parse_records(content: &str, format: RecordFormat, local: &LocalFiles) -> Vec<Record> {
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| match format {
+            RecordFormat::Lines => Record {
+                id: index.to_string(),
+                messages: Ok(vec![Message::user(line)]),
+            },
+            RecordFormat::NDJSON => parse_ndjson_record(index, line, local),
+        })
+        .collect()
+}
+
+fn parse_ndjson_record(index: usize, line: &str, local: &LocalFiles) -> Record {
+    let invalid = |reason: String| Error::InvalidMessageRecord { index, reason };
+
+    let input = match serde_json::from_str::<NDJSONInput>(line) {
+        Ok(input) => input,
+        Err(e) => {
+            return Record {
+                id: index.to_string(),
+                messages: Err(invalid(e.to_string())),
+            };
+        }
+    };
+
+    let id = match input.id {
+        Some(Value::String(id)) => id,
+        Some(id) => id.to_string(),
+        None => index.to_string(),
+    };
+
+    let messages = match (input.input, input.messages) {
+        (Some(text), None) => Ok(vec![Message::user(&text)]),
+        (None, Some(messages)) => messages
+            .into_iter()
+            .map(parse::check_tool_call_id)
+            .collect::<Result<_, _>>()
+            .map_err(invalid)
+            .and_then(|messages| {
+                parse::load_attachments(messages, local).map_err(|e| invalid(e.to_string()))
+            }),
+        _ => Err(invalid(
+            "expected exactly one of \"input\" or \"messages\"".to_string(),
+        )),
+    };
+
+    Record { id, messages }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_line_records() {
+        let records = parse_records(
+            "first\n\n  \nsecond\n",
+            RecordFormat::Lines,
+            &LocalFiles::Deny,
+        );
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "0");
+        assert_eq!(
+            records[0].messages.as_ref().unwrap(),
+            &[Message::user("first")]
+        );
+        assert_eq!(records[1].id, "1");
+        assert_eq!(
+            records[1].messages.as_ref().unwrap(),
+            &[Message::user("second")]
+        );
+    }
+
+    #[test]
+    fn parse_ndjson_records() {
+        let content = r#"{"id": "a", "input": "first"}
+{"id": 7, "messages": [{"role": "user", "content": "second"}]}
+{"input": "third"}
+"#;
+        let records = parse_records(content, RecordFormat::NDJSON, &LocalFiles::Deny);
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].id, "a");
+        assert_eq!(
+            records[0].messages.as_ref().unwrap(),
+            &[Message::user("first")]
+        );
+        assert_eq!(records[1].id, "7");
+        assert_eq!(
+            records[1].messages.as_ref().unwrap(),
+            &[Message::user("second")]
+        );
+        assert_eq!(records[2].id, "2");
+        assert_eq!(
+            records[2].messages.as_ref().unwrap(),
+            &[Message::user("third")]
+        );
+    }
+
+    #[test]
+    fn parse_ndjson_invalid_records() {
+        let content = r#"not json
+{"id": "b"}
+{"id": "c", "input": "x", "messages": []}
+{"id": "d", "messages": [{"role": "tool", "content": "Sunny"}]}
+"#;
+        let records = parse_records(content, RecordFormat::NDJSON, &LocalFiles::Deny);
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].id, "0");
+        assert!(matches!(
+            records[0].messages,
+            Err(Error::InvalidMessageRecord { index: 0, .. })
+        ));
+        assert_eq!(records[1].id, "b");
+        assert!(matches!(
+            records[1].messages,
+            Err(Error::InvalidMessageRecord { index: 1, .. })
+        ));
+        assert_eq!(records[2].id, "c");
+        assert!(records[2].messages.is_err());
+        assert!(
+            matches!(&records[3].messages, Err(Error::InvalidMessageRecord { index: 3, reason }) if reason.contains("tool_call_id")),
+            "unexpected records: {records:?}"
+        );
+    }
+
+    #[test]
+    fn parse_ndjson_local_attachments() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "hi").unwrap();
+        let content = r#"{"messages": [{"role": "user", "content": "x", "attachments": [{"type": "file", "source": "notes.txt"}]}]}"#;
+
+        let records = parse_records(content, RecordFormat::NDJSON, &LocalFiles::Deny);
+        assert!(
+            matches!(&records[0].messages, Err(Error::InvalidMessageRecord { reason, .. }) if reason.contains("--allow-local-attachments")),
+            "unexpected records: {records:?}"
+        );
+
+        let local = LocalFiles::Allow(dir.path().to_path_buf());
+        let records = parse_records(content, RecordFormat::NDJSON, &local);
+        let messages = records[0].messages.as_ref().unwrap();
+        assert_eq!(
+            messages[0].attachments[0].url,
+            "data:text/plain;base64,aGk="
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! Implements cache subcommand
+
+use std::io::Write;
+
+use anyhow::{Context, Result};
+
+use crate::cache;
+use crate::cli::CacheCommand;
+
+/// Run `cmd`, writing output to `dest`
+pub fn exec(mut dest: impl Write, cmd: CacheCommand) -> Result<()> {
+    match cmd {
+        CacheCommand::Stats => {
+            let stats = cache::stats()?;
+            let output =
+                toml::to_string(&stats).with_context(|| "failed to serialize cache stats")?;
+            write!(dest, "{output}")?;
+        }
+        CacheCommand::Clear => {
+            let removed = cache::clear()?;
+            writeln!(dest, "removed {removed} cached responses")?;
+        }
+    }
+    Ok(())
+}
@@ -1,31 +1,214 @@
 //! Implements chat subcommand
 
 use crate::Error;
-use crate::cli::{Invocation, OutputFormat};
-use crate::openai::{self, FinishReason, Message, Reasoning, Response};
-use crate::parse;
+use crate::attachment::{Attachment, LocalFiles};
+use crate::cache::{Cache, CachedProvider};
+use crate::cli::{Invocation, OnIncomplete, OutputFormat};
+use crate::config::{self, Price, Settings};
+use crate::ledger::LedgerProvider;
+use crate::openai::{
+    self, FinishReason, Message, Reasoning, Response, Role, StreamEvent, ToolCall, Usage,
+};
+use crate::parse::{self, InputFormat};
+use crate::provider::{self, Provider, ProviderKind};
+use crate::schema;
+use crate::session::{self, Session};
+use crate::template;
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::fs::File;
 use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Lines emitted for `OutputFormat::NDJSON`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum NDJSONRecord<'a> {
+    /// Incremental text while streaming
+    Delta {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<&'a str>,
+        delta: &'a str,
+    },
+    /// Final response
+    Response {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        response_id: Option<&'a str>,
+        text: &'a str,
+        model: &'a str,
+        usage: &'a Usage,
+        finish_reason: &'a FinishReason,
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        tool_calls: &'a [ToolCall],
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        cached: bool,
+    },
+    /// Failed request, e.g. for a record in batch mode
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<&'a str>,
+        error: String,
+    },
+}
 
 /// Executes `Invocation` via given args
 pub async fn exec(args: Invocation) -> Result<()> {
-    let base_url =
-        std::env::var("OPENAI_API_ENDPOINT").unwrap_or("https://api.openai.com".to_string());
-
-    let client = openai::Client::new(args.api_key.clone(), base_url)
-        .with_context(|| "failed to create http client")?;
-
-    let file_msgs = read_messages_from_file(&args.file)
-        .with_context(|| format!("failed to open {}", &args.file))?;
+    let provider = create_provider(&args)?;
 
-    let msgs = [args.messages.clone(), file_msgs].concat();
+    let msgs = input_messages(&args)?;
 
     if msgs.is_empty() {
         return Err(Error::NoMessagesProvided.into());
     }
 
+    let request = build_request(&args, msgs.clone())?;
+
+    // Output is only streamed if it is printed regardless of how the response ends
+    let stream =
+        args.stream && args.text_format.is_none() && args.on_incomplete == OnIncomplete::Print;
+
+    let res = if stream {
+        stream_response(provider.as_ref(), &request, &args, &msgs).await?
+    } else {
+        let res = provider
+            .create_response(&request)
+            .await
+            .with_context(|| "failed to fetch request")?;
+
+        if args.on_incomplete == OnIncomplete::Fail {
+            check_complete(&res)?;
+        }
+        validate_response(&args, &res)?;
+        show_response(io::stdout(), &args, args.id.as_deref(), &msgs, &res)?;
+        res
+    };
+
+    if args.usage {
+        show_usage("usage", &res.usage, args.price.as_ref());
+    }
+    show_response_id(&args, &res)?;
+    check_complete(&res)?;
+
+    if let Some(name) = &args.session {
+        save_session(name, &args, msgs, &res)
+            .with_context(|| format!("failed to save session {name}"))?;
+    }
+    Ok(())
+}
+
+/// Print response id to stderr, and write it to `args.id_file`, as requested
+fn show_response_id(args: &Invocation, res: &Response) -> Result<()> {
+    if !args.print_id && args.id_file.is_none() {
+        return Ok(());
+    }
+
+    let id = res.id.as_deref().with_context(|| {
+        format!(
+            "{} provider returned no response id",
+            args.provider.as_str()
+        )
+    })?;
+
+    if args.print_id {
+        eprintln!("{id}");
+    }
+    if let Some(file) = &args.id_file {
+        std::fs::write(file, format!("{id}\n"))
+            .with_context(|| format!("failed to write response id to {file}"))?;
+    }
+    Ok(())
+}
+
+/// Save `msgs` and reply in `res` as session `name`, with model settings of `args`
+fn save_session(name: &str, args: &Invocation, msgs: Vec<Message>, res: &Response) -> Result<()> {
+    let reply = single_choice(res)?.message.clone();
+    let mut usage = args.history_usage.clone();
+    usage += &res.usage;
+    let session = Session {
+        settings: Settings {
+            provider: Some(args.provider),
+            model: Some(args.model.clone()),
+            temperature: args.temperature,
+            reasoning_effort: args.reasoning_effort,
+            ..Settings::default()
+        },
+        messages: [msgs, vec![reply]].concat(),
+        usage,
+    };
+    session.save(name)?;
+
+    if args.usage {
+        show_usage(
+            &format!("session {name}"),
+            &session.usage,
+            args.price.as_ref(),
+        );
+    }
+    Ok(())
+}
+
+/// Create provider selected by `args`, running the configured API key command if no key is given.
+/// Requests are recorded in ledger and limited by `args.budget`, and provider is wrapped in cache if
+/// `args.cache` is set, so cached responses aren't counted as spend
+pub(crate) fn create_provider(args: &Invocation) -> Result<Box<dyn Provider>> {
+    let api_key = match (&args.api_key, &args.api_key_cmd) {
+        (None, Some(cmd)) => Some(config::run_key_command(cmd)?),
+        (api_key, _) => api_key.clone(),
+    };
+
+    let provider = provider::create(
+        args.provider,
+        api_key,
+        args.endpoint.as_deref(),
+        args.mock_rules.as_deref(),
+        args.retry_policy.clone(),
+    )
+    .with_context(|| "failed to create http client")?;
+
+    // Mock replies cost nothing, so aren't recorded or counted against budget
+    let provider: Box<dyn Provider> = match args.provider {
+        ProviderKind::Mock => provider,
+        _ => Box::new(LedgerProvider::new(
+            provider,
+            args.provider,
+            args.profile.clone(),
+            args.price.clone(),
+            args.budget,
+        )?),
+    };
+
+    if !args.cache {
+        return Ok(provider);
+    }
+
+    let cache = Cache::new(args.cache_ttl)?;
+    // Mock replies depend on its rules file rather than an endpoint
+    let endpoint = provider::endpoint(args.provider, args.endpoint.as_deref())
+        .or_else(|| args.mock_rules.clone())
+        .unwrap_or_default();
+    Ok(Box::new(CachedProvider::new(
+        provider,
+        cache,
+        args.provider,
+        endpoint,
+    )))
+}
+
+/// Build request for `msgs` using model settings in `args`
+pub(crate) fn build_request(
+    args: &Invocation,
+    msgs: Vec<Message>,
+) -> Result<openai::ResponseRequest> {
+    if args.previous_response_id.is_some() && args.provider != ProviderKind::OpenAI {
+        anyhow::bail!(
+            "--continue is not supported by {} provider",
+            args.provider.as_str()
+        );
+    }
+
     // TODO: Lifetimes for `ResponseRequest` fields
     let mut builder = openai::ResponseRequest::builder();
 
@@ -33,77 +216,298 @@ pub async fn exec(args: Invocation) -> Result<()> {
         .model(args.model.clone())
         .messages(msgs)
         .temperature(args.temperature)
-        .timeout(args.timeout);
+        .timeout(args.timeout)
+        .text_format(args.text_format.clone())
+        .tools(args.tools.clone())
+        .max_output_tokens(args.max_output_tokens)
+        .previous_response_id(args.previous_response_id.clone())
+        .store(args.store);
 
     if let Some(effort) = args.reasoning_effort {
         builder.reasoning(Some(Reasoning::from_effort(effort)));
     }
 
-    let request = builder
-        .build()
-        .with_context(|| "failed to create request")?;
+    builder.build().with_context(|| "failed to create request")
+}
 
-    let res = client
-        .create_response(&request)
-        .await
-        .with_context(|| "failed to fetch request")?;
+/// Validate response text against structured output format requested in `args`, if any.
+/// Responses calling tools carry no output to validate.
+pub(crate) fn validate_response(args: &Invocation, resp: &Response) -> Result<(), Error> {
+    let choice = single_choice(resp)?;
+    match &args.text_format {
+        Some(format) if choice.finish_reason == FinishReason::Stop => {
+            schema::validate(format, &choice.message.content)
+        }
+        _ => Ok(()),
+    }
+}
 
-    show_response(io::stdout(), &args, &res)?;
-    Ok(())
+/// Streams response. Plaintext and NDJSON output is written to stdout as deltas arrive, while
+/// other formats are shown once the final response is available.
+async fn stream_response(
+    provider: &dyn Provider,
+    request: &openai::ResponseRequest,
+    args: &Invocation,
+    msgs: &[Message],
+) -> Result<Response> {
+    let mut stdout = io::stdout();
+
+    let res = match args.output_format {
+        OutputFormat::Plaintext => {
+            let mut has_text = false;
+            let res = provider
+                .stream_response(request, &mut |event| {
+                    if let StreamEvent::OutputTextDelta(delta) = event {
+                        has_text = true;
+                        stdout.write_all(delta.as_bytes()).map_err(Error::IO)?;
+                        stdout.flush().map_err(Error::IO)?;
+                    }
+                    Ok(())
+                })
+                .await
+                .with_context(|| "failed to fetch request")?;
+
+            let choice = single_choice(&res)?;
+            if has_text || choice.message.tool_calls.is_empty() {
+                writeln!(stdout).map_err(Error::IO)?;
+            }
+            write_tool_calls(&mut stdout, &choice.message.tool_calls)?;
+            res
+        }
+        OutputFormat::NDJSON => {
+            let id = args.id.as_deref();
+            let res = provider
+                .stream_response(request, &mut |event| {
+                    if let StreamEvent::OutputTextDelta(delta) = event {
+                        let record = NDJSONRecord::Delta { id, delta };
+                        serde_json::to_writer(&mut stdout, &record)?;
+                        writeln!(stdout)?;
+                        stdout.flush()?;
+                    }
+                    Ok(())
+                })
+                .await
+                .with_context(|| "failed to fetch request")?;
+            show_response(stdout, args, args.id.as_deref(), msgs, &res)?;
+            res
+        }
+        _ => {
+            let res = provider
+                .stream_response(request, &mut |_| Ok(()))
+                .await
+                .with_context(|| "failed to fetch request")?;
+            show_response(stdout, args, args.id.as_deref(), msgs, &res)?;
+            res
+        }
+    };
+
+    Ok(res)
+}
+
+/// Messages from flags, followed by `args.template` rendered with `args.vars` and messages read
+/// from `args.file`. If template uses `{{input}}`, contents of `args.file` are bound to it instead
+fn new_messages(args: &Invocation) -> Result<Vec<Message>> {
+    let Some(template) = &args.template else {
+        let file_msgs =
+            read_messages_from_file(&args.file, args.input_format, args.allow_local_attachments)
+                .with_context(|| format!("failed to open {}", &args.file))?;
+        return Ok([args.messages.clone(), file_msgs].concat());
+    };
+
+    let mut vars = args.vars.clone();
+    let mut file_msgs = vec![];
+    if template.variables().contains(template::INPUT_VAR) {
+        if !vars.contains_key(template::INPUT_VAR)
+            && let Some(mut r) =
+                open_file(&args.file).with_context(|| format!("failed to open {}", &args.file))?
+        {
+            let mut input = String::new();
+            r.read_to_string(&mut input)?;
+            vars.insert(template::INPUT_VAR.to_string(), input);
+        }
+    } else {
+        file_msgs =
+            read_messages_from_file(&args.file, args.input_format, args.allow_local_attachments)
+                .with_context(|| format!("failed to open {}", &args.file))?;
+    }
+
+    let mut template_msgs = template.render(&vars)?;
+
+    // System prompt given via flag replaces template's
+    if args.messages.iter().any(|m| m.role == Role::System) {
+        template_msgs.retain(|m| m.role != Role::System);
+    }
+
+    Ok([args.messages.clone(), template_msgs, file_msgs].concat())
 }
 
-/// Read messages from non-tty stdin or file specified by `args.file`
-fn read_messages_from_file(file: &str) -> Result<Vec<Message>> {
-    let reader: Option<Box<dyn Read>> = match file {
+/// Messages of `args.history`, followed by messages from flags, template, and file
+pub(crate) fn input_messages(args: &Invocation) -> Result<Vec<Message>> {
+    let mut msgs = new_messages(args)?;
+    attach(&mut msgs, &args.attachments);
+    Ok(session::with_history(&args.history, msgs))
+}
+
+/// Append `attachments` to last user message of `msgs`, or to a new user message if there is none
+pub(crate) fn attach(msgs: &mut Vec<Message>, attachments: &[Attachment]) {
+    if attachments.is_empty() {
+        return;
+    }
+
+    match msgs.iter_mut().rfind(|m| m.role == Role::User) {
+        Some(msg) => msg.attachments.extend_from_slice(attachments),
+        None => msgs.push(Message {
+            attachments: attachments.to_vec(),
+            ..Message::user("")
+        }),
+    }
+}
+
+/// Read messages from non-tty stdin or file specified by `args.file`. Attachments of local files
+/// are resolved relative to `file`, and are only allowed from stdin if `allow_local`
+pub(crate) fn read_messages_from_file(
+    file: &str,
+    format: InputFormat,
+    allow_local: bool,
+) -> Result<Vec<Message>> {
+    // Files given by path are trusted, unlike stdin which may be piped from anywhere
+    let local = local_files(file, allow_local || file != "-");
+    match open_file(file)? {
+        None => Ok(vec![]),
+        Some(mut r) => Ok(parse::parse_messages_with(&mut r, format, &local)?),
+    }
+}
+
+/// Local files that attachments in input from `file` may read if `allowed`, relative to `file`
+pub(crate) fn local_files(file: &str, allowed: bool) -> LocalFiles {
+    match (file, allowed) {
+        (_, false) => LocalFiles::Deny,
+        ("-", true) => LocalFiles::Allow(PathBuf::new()),
+        (file, true) => LocalFiles::Allow(
+            Path::new(file)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+/// Reader for `file`, or for stdin if `file` is "-" and stdin is not a tty
+fn open_file(file: &str) -> io::Result<Option<Box<dyn Read>>> {
+    match file {
         "-" => {
             let stdin = io::stdin();
             if stdin.is_terminal() {
-                None
+                Ok(None)
             } else {
-                Some(Box::new(stdin))
+                Ok(Some(Box::new(stdin)))
             }
         }
-        file => Some(Box::new(File::open(file)?)),
-    };
+        file => Ok(Some(Box::new(File::open(file)?))),
+    }
+}
 
-    match reader {
-        None => Ok(vec![]),
-        Some(mut r) => Ok(parse::parse_messages(&mut r)?),
+/// Returns the single choice of response
+pub(crate) fn single_choice(resp: &Response) -> Result<&openai::Choice, Error> {
+    match resp.choices.len() {
+        1 => Ok(&resp.choices[0]),
+        _ => Err(Error::UnexpectedResponse(format!(
+            "Unexpected number of choices in response: {:?}",
+            resp
+        ))),
+    }
+}
+
+/// Print `usage` to stderr, with estimated cost if `price` is known
+pub(crate) fn show_usage(label: &str, usage: &Usage, price: Option<&Price>) {
+    eprintln!("{label}: {}", format_usage(usage, price));
+}
+
+/// Summary of `usage`, e.g. `8 input (2 cached), 9 output (4 reasoning), 17 total tokens`
+fn format_usage(usage: &Usage, price: Option<&Price>) -> String {
+    let mut summary = format!("{} input", usage.input_tokens);
+    if usage.cached_tokens != 0 {
+        summary.push_str(&format!(" ({} cached)", usage.cached_tokens));
+    }
+    summary.push_str(&format!(", {} output", usage.output_tokens));
+    if usage.reasoning_tokens != 0 {
+        summary.push_str(&format!(" ({} reasoning)", usage.reasoning_tokens));
     }
+    summary.push_str(&format!(", {} total tokens", usage.total_tokens));
+    if let Some(price) = price {
+        summary.push_str(&format!(", ${:.6} estimated", price.cost(usage)));
+    }
+    summary
 }
 
-/// Show formatted output for a Responses API result
-fn show_response(dest: impl Write, args: &Invocation, resp: &Response) -> Result<(), Error> {
+/// Errors if response was cut short by output token limit or content filter
+pub(crate) fn check_complete(resp: &Response) -> Result<(), Error> {
+    match single_choice(resp)?.finish_reason {
+        FinishReason::Length => Err(Error::IncompleteResponse(
+            "output token limit reached".to_string(),
+        )),
+        FinishReason::ContentFilter => Err(Error::IncompleteResponse(
+            "output stopped by content filter".to_string(),
+        )),
+        FinishReason::Stop | FinishReason::FunctionCall => Ok(()),
+    }
+}
+
+/// Show formatted output for a Responses API result, given the input messages `msgs` and
+/// caller-supplied `id`
+pub(crate) fn show_response(
+    dest: impl Write,
+    args: &Invocation,
+    id: Option<&str>,
+    msgs: &[Message],
+    resp: &Response,
+) -> Result<(), Error> {
     let mut writer = BufWriter::new(dest);
-    let choice = match resp.choices.len() {
-        1 => &resp.choices[0],
-        _ => {
-            return Err(Error::UnexpectedResponse(format!(
-                "Unexpected number of choices in response: {:?}",
-                resp
-            )));
-        }
-    };
+    let choice = single_choice(resp)?;
 
-    match choice.finish_reason {
-        FinishReason::Stop => {
-            let output = match args.output_format {
-                OutputFormat::Plaintext => choice.message.content.to_string(),
-                OutputFormat::JSON => serde_json::to_string(resp).map_err(Error::JSON)?,
-                OutputFormat::JSONPretty => {
-                    serde_json::to_string_pretty(resp).map_err(Error::JSON)?
-                }
+    let output = match args.output_format {
+        OutputFormat::Plaintext if !choice.message.tool_calls.is_empty() => {
+            // Text accompanying tool calls precedes them, as when streamed
+            if !choice.message.content.is_empty() {
+                writeln!(writer, "{}", choice.message.content).map_err(Error::IO)?;
+            }
+            return write_tool_calls(&mut writer, &choice.message.tool_calls);
+        }
+        OutputFormat::Plaintext => choice.message.content.to_string(),
+        OutputFormat::JSON => serde_json::to_string(resp).map_err(Error::JSON)?,
+        OutputFormat::JSONPretty => serde_json::to_string_pretty(resp).map_err(Error::JSON)?,
+        OutputFormat::NDJSON => {
+            let record = NDJSONRecord::Response {
+                id,
+                response_id: resp.id.as_deref(),
+                text: &choice.message.content,
+                model: &resp.model,
+                usage: &resp.usage,
+                finish_reason: &choice.finish_reason,
+                tool_calls: &choice.message.tool_calls,
+                cached: resp.cached,
             };
-            writeln!(writer, "{}", output).map_err(Error::IO)?
+            serde_json::to_string(&record).map_err(Error::JSON)?
         }
-        _ => {
-            return Err(Error::UnexpectedResponse(format!(
-                "Received unrecognized stop reason for choice: {:?}",
-                choice
-            )));
+        OutputFormat::Transcript => {
+            let transcript = [msgs, std::slice::from_ref(&choice.message)].concat();
+            return parse::write_transcript(&mut writer, &transcript);
         }
-    }
+    };
+    writeln!(writer, "{}", output).map_err(Error::IO)?;
+
+    Ok(())
+}
 
+/// Write one `call_id<TAB>name<TAB>arguments` line per tool call, with arguments as compact JSON
+fn write_tool_calls(dest: &mut impl Write, calls: &[ToolCall]) -> Result<(), Error> {
+    for call in calls {
+        let arguments = match serde_json::from_str::<serde_json::Value>(&call.arguments) {
+            Ok(value) => value.to_string(),
+            Err(_) => call.arguments.replace(['\n', '\t'], " "),
+        };
+        writeln!(dest, "{}\t{}\t{}", call.call_id, call.name, arguments)?;
+    }
     Ok(())
 }
 
@@ -117,7 +521,7 @@ mod test {
 
     use crate::{
         cli::{Invocation, InvocationBuilder, OutputFormat},
-        openai::{Choice, FinishReason, Message, Response, ResponseBuilder, Usage},
+        openai::{Choice, FinishReason, Message, Response, ResponseBuilder, ToolCall, Usage},
     };
 
     use super::*;
@@ -137,7 +541,7 @@ mod test {
             }])
             .build()?;
 
-        let res = show_response(&mut output, &args, &resp);
+        let res = show_response(&mut output, &args, None, &[], &resp);
 
         assert!(res.is_ok(), "Showing response should succeed");
         assert_eq!(output, b"Hello world\n");
@@ -156,7 +560,7 @@ mod test {
             }])
             .build()?;
 
-        let _ = show_response(&mut output, &args, &resp);
+        let _ = show_response(&mut output, &args, None, &[], &resp);
         let output = String::from_utf8(output).expect("Should be valid string");
 
         let is_json = str::starts_with("{")
@@ -180,7 +584,7 @@ mod test {
             }])
             .build()?;
 
-        let _ = show_response(&mut output, &args, &resp);
+        let _ = show_response(&mut output, &args, None, &[], &resp);
         let output = String::from_utf8(output).expect("Should be valid string");
 
         let is_json = str::starts_with("{\n")
@@ -191,6 +595,173 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn show_chat_response_transcript() -> Result<()> {
+        let mut output = vec![];
+        let args = default_args()
+            .output_format(OutputFormat::Transcript)
+            .build()?;
+        let resp = default_resp()
+            .choices(vec![Choice {
+                message: Message::assistant("Hello world"),
+                finish_reason: FinishReason::Stop,
+            }])
+            .build()?;
+        let msgs = vec![Message::system("SYSTEM"), Message::user("Hello")];
+
+        show_response(&mut output, &args, None, &msgs, &resp)?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            ">>> system\nSYSTEM\n\n>>> user\nHello\n\n>>> assistant\nHello world\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn show_chat_response_ndjson() -> Result<()> {
+        let mut output = vec![];
+        let args = default_args()
+            .output_format(OutputFormat::NDJSON)
+            .id(Some("line-1".to_string()))
+            .build()?;
+        let resp = default_resp()
+            .model("gpt-5".to_string())
+            .choices(vec![Choice {
+                message: Message::assistant("Hello\nworld"),
+                finish_reason: FinishReason::Stop,
+            }])
+            .build()?;
+
+        show_response(&mut output, &args, args.id.as_deref(), &[], &resp)?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            r#"{"type":"response","id":"line-1","text":"Hello\nworld","model":"gpt-5","usage":{"input_tokens":0,"output_tokens":0,"total_tokens":0},"finish_reason":"stop"}"#
+                .to_string()
+                + "\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn show_chat_response_tool_calls_plaintext() -> Result<()> {
+        let mut output = vec![];
+        let args = default_args()
+            .output_format(OutputFormat::Plaintext)
+            .build()?;
+        let resp = default_resp()
+            .choices(vec![Choice {
+                message: Message::tool_calls(vec![
+                    ToolCall {
+                        call_id: "call_1".to_string(),
+                        name: "get_weather".to_string(),
+                        arguments: "{\n  \"city\": \"Tokyo\"\n}".to_string(),
+                    },
+                    ToolCall {
+                        call_id: "call_2".to_string(),
+                        name: "get_time".to_string(),
+                        arguments: "{}".to_string(),
+                    },
+                ]),
+                finish_reason: FinishReason::FunctionCall,
+            }])
+            .build()?;
+
+        show_response(&mut output, &args, None, &[], &resp)?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            "call_1\tget_weather\t{\"city\":\"Tokyo\"}\ncall_2\tget_time\t{}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn show_chat_response_text_and_tool_calls_plaintext() -> Result<()> {
+        let mut output = vec![];
+        let args = default_args()
+            .output_format(OutputFormat::Plaintext)
+            .build()?;
+        let resp = default_resp()
+            .choices(vec![Choice {
+                message: Message {
+                    content: "Checking the time".to_string(),
+                    ..Message::tool_calls(vec![ToolCall {
+                        call_id: "call_1".to_string(),
+                        name: "get_time".to_string(),
+                        arguments: "{}".to_string(),
+                    }])
+                },
+                finish_reason: FinishReason::FunctionCall,
+            }])
+            .build()?;
+
+        show_response(&mut output, &args, None, &[], &resp)?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            "Checking the time\ncall_1\tget_time\t{}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn show_chat_response_tool_calls_ndjson() -> Result<()> {
+        let mut output = vec![];
+        let args = default_args().output_format(OutputFormat::NDJSON).build()?;
+        let resp = default_resp()
+            .model("gpt-5".to_string())
+            .choices(vec![Choice {
+                message: Message::tool_calls(vec![ToolCall {
+                    call_id: "call_1".to_string(),
+                    name: "get_time".to_string(),
+                    arguments: "{}".to_string(),
+                }]),
+                finish_reason: FinishReason::FunctionCall,
+            }])
+            .build()?;
+
+        show_response(&mut output, &args, None, &[], &resp)?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            r#"{"type":"response","text":"","model":"gpt-5","usage":{"input_tokens":0,"output_tokens":0,"total_tokens":0},"finish_reason":"function_call","tool_calls":[{"call_id":"call_1","name":"get_time","arguments":"{}"}]}"#
+                .to_string()
+                + "\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn format_usage_details_and_cost() {
+        let usage = Usage {
+            input_tokens: 8,
+            output_tokens: 9,
+            total_tokens: 17,
+            ..Usage::default()
+        };
+        assert_eq!(
+            format_usage(&usage, None),
+            "8 input, 9 output, 17 total tokens"
+        );
+
+        let usage = Usage {
+            cached_tokens: 2,
+            reasoning_tokens: 4,
+            ..usage
+        };
+        let price = Price {
+            input: 1000.0,
+            cached_input: None,
+            output: 2000.0,
+        };
+        assert_eq!(
+            format_usage(&usage, Some(&price)),
+            "8 input (2 cached), 9 output (4 reasoning), 17 total tokens, $0.026000 estimated"
+        );
+    }
+
     fn default_args() -> InvocationBuilder {
         Invocation::builder()
             .api_key(Some(String::default()))
@@ -0,0 +1,156 @@
+//! Implements config subcommand
+
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::cli::Invocation;
+use crate::config;
+use crate::openai::{ReasoningEffort, Role};
+use crate::provider::{self, ProviderKind};
+
+/// Shown in place of API keys
+const REDACTED: &str = "<redacted>";
+
+/// Effective settings shown by `config show`
+#[derive(Debug, Serialize)]
+struct EffectiveSettings<'a> {
+    config: Option<String>,
+    profile: Option<&'a str>,
+    provider: ProviderKind,
+    model: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    endpoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mock_rules: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    timeout: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<ReasoningEffort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
+    cache: bool,
+    cache_ttl: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    budget: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    daily_budget: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    monthly_budget: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key_cmd: Option<&'a str>,
+}
+
+/// Write effective settings of `args` as TOML, with API key redacted
+pub fn show(mut dest: impl Write, args: &Invocation) -> Result<()> {
+    let settings = EffectiveSettings {
+        config: config::path().map(|p| p.display().to_string()),
+        profile: args.profile.as_deref(),
+        provider: args.provider,
+        model: &args.model,
+        endpoint: provider::endpoint(args.provider, args.endpoint.as_deref()),
+        mock_rules: match args.provider {
+            ProviderKind::Mock => args.mock_rules.as_deref(),
+            _ => None,
+        },
+        // Widen via decimal representation, so `0.2` isn't shown as `0.20000000298023224`
+        temperature: args.temperature.and_then(|t| t.to_string().parse().ok()),
+        timeout: args.timeout.as_secs(),
+        reasoning_effort: args.reasoning_effort,
+        system: args
+            .messages
+            .iter()
+            .find(|m| m.role == Role::System)
+            .map(|m| m.content.as_str()),
+        cache: args.cache,
+        cache_ttl: args.cache_ttl.as_secs(),
+        budget: args.budget.invocation,
+        daily_budget: args.budget.daily,
+        monthly_budget: args.budget.monthly,
+        api_key: args.api_key.as_ref().map(|_| REDACTED),
+        api_key_cmd: args.api_key_cmd.as_deref(),
+    };
+
+    let output = toml::to_string(&settings).with_context(|| "failed to serialize settings")?;
+    write!(dest, "{output}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::cli::OutputFormat;
+    use crate::openai::Message;
+
+    #[test]
+    fn show_redacts_api_key() -> Result<()> {
+        let args = Invocation::builder()
+            .api_key(Some("sk-SECRET".to_string()))
+            .messages(vec![Message::system("Summarize"), Message::user("USER")])
+            .provider(ProviderKind::Ollama)
+            .endpoint(Some("http://gpu-box:11434".to_string()))
+            .profile(Some("local".to_string()))
+            .model("llama3".to_string())
+            .temperature(Some(0.2))
+            .output_format(OutputFormat::Plaintext)
+            .timeout(Duration::from_secs(120))
+            .file("-".to_string())
+            .build()?;
+
+        let mut output = vec![];
+        show(&mut output, &args)?;
+        let output = String::from_utf8(output)?;
+
+        assert!(
+            !output.contains("SECRET"),
+            "key should be redacted: {output}"
+        );
+        for line in [
+            "profile = \"local\"",
+            "provider = \"ollama\"",
+            "model = \"llama3\"",
+            "endpoint = \"http://gpu-box:11434\"",
+            "temperature = 0.2",
+            "timeout = 120",
+            "system = \"Summarize\"",
+            "api_key = \"<redacted>\"",
+        ] {
+            assert!(output.contains(line), "missing {line}: {output}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn show_mock_rules_instead_of_endpoint() -> Result<()> {
+        let args = Invocation::builder()
+            .api_key(None)
+            .messages(vec![])
+            .provider(ProviderKind::Mock)
+            .mock_rules(Some("rules.toml".to_string()))
+            .model("test".to_string())
+            .output_format(OutputFormat::Plaintext)
+            .timeout(Duration::from_secs(120))
+            .file("-".to_string())
+            .build()?;
+
+        let mut output = vec![];
+        show(&mut output, &args)?;
+        let output = String::from_utf8(output)?;
+
+        assert!(
+            output.contains("mock_rules = \"rules.toml\""),
+            "missing mock_rules: {output}"
+        );
+        assert!(
+            !output.contains("endpoint"),
+            "mock provider has no endpoint: {output}"
+        );
+        Ok(())
+    }
+}
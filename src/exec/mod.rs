@@ -1,10 +1,24 @@
 //! Executor for cogni
+pub mod agent;
+pub mod batch;
+pub mod cache;
 pub mod chat;
+pub mod config;
+pub mod session;
+pub mod usage;
 
-use crate::cli::Invocation;
+use crate::cli::{Invocation, Mode};
 use anyhow::Result;
 
 /// Execute the invocation
 pub async fn exec(inv: Invocation) -> Result<()> {
-    chat::exec(inv).await
+    match inv.mode.clone() {
+        Mode::Chat => chat::exec(inv).await,
+        Mode::Batch(batch) => batch::exec(inv, batch).await,
+        Mode::Agent(agent) => agent::exec(inv, agent).await,
+        Mode::ShowConfig => config::show(std::io::stdout(), &inv),
+        Mode::Session(cmd) => session::exec(std::io::stdout(), cmd),
+        Mode::Cache(cmd) => cache::exec(std::io::stdout(), cmd),
+        Mode::Usage(cmd) => usage::exec(std::io::stdout(), cmd),
+    }
 }
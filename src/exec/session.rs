@@ -0,0 +1,27 @@
+//! Implements session subcommand
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::cli::SessionCommand;
+use crate::parse;
+use crate::session;
+
+/// Run `cmd`, writing output to `dest`
+pub fn exec(mut dest: impl Write, cmd: SessionCommand) -> Result<()> {
+    match cmd {
+        SessionCommand::List => {
+            for name in session::list()? {
+                writeln!(dest, "{name}")?;
+            }
+        }
+        SessionCommand::Show(name) => {
+            let session = session::load_existing(&name)?;
+            parse::write_transcript(&mut dest, &session.messages)?;
+        }
+        SessionCommand::Remove(name) => session::remove(&name)?,
+        SessionCommand::Fork { from, to } => session::fork(&from, &to)?,
+    }
+    Ok(())
+}
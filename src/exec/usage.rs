@@ -0,0 +1,79 @@
+//! Implements usage subcommand
+
+use std::io::Write;
+
+use anyhow::Result;
+use chrono::{TimeDelta, Utc};
+
+use crate::cli::UsageCommand;
+use crate::ledger::{self, GroupBy, Summary};
+
+/// Run `cmd`, writing output to `dest`
+pub fn exec(dest: impl Write, cmd: UsageCommand) -> Result<()> {
+    match cmd {
+        UsageCommand::Report { since, group_by } => {
+            // Durations reaching before the earliest representable time have no lower bound
+            let since = since
+                .and_then(|since| TimeDelta::from_std(since).ok())
+                .and_then(|since| Utc::now().checked_sub_signed(since));
+            let summaries = ledger::summarize(&ledger::read()?, since, group_by);
+            write_report(dest, group_by, &summaries)
+        }
+    }
+}
+
+/// Write `summaries` as table with aligned columns, followed by their total
+fn write_report(mut dest: impl Write, group_by: GroupBy, summaries: &[Summary]) -> Result<()> {
+    let total = Summary::total("total", summaries);
+    let header = match group_by {
+        GroupBy::Model => "model",
+        GroupBy::Provider => "provider",
+        GroupBy::Profile => "profile",
+        GroupBy::Day => "day",
+    };
+    let mut rows = vec![
+        [
+            header,
+            "requests",
+            "input",
+            "cached",
+            "output",
+            "reasoning",
+            "total",
+            "cost",
+        ]
+        .map(String::from),
+    ];
+    for summary in summaries.iter().chain([&total]) {
+        rows.push([
+            summary.key.clone(),
+            summary.requests.to_string(),
+            summary.usage.input_tokens.to_string(),
+            summary.usage.cached_tokens.to_string(),
+            summary.usage.output_tokens.to_string(),
+            summary.usage.reasoning_tokens.to_string(),
+            summary.usage.total_tokens.to_string(),
+            summary
+                .cost
+                .map(|cost| format!("${cost:.4}"))
+                .unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+
+    let mut widths = [0; 8];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(dest, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,536 @@
+//! Append-only ledger of requests sent to providers, with their estimated cost
+//!
+//! Each request is appended as a JSON line to `$XDG_STATE_HOME/cogni/ledger.ndjson`, with its
+//! model, profile, token usage, and cost estimated from prices in config. Spend recorded in the
+//! ledger is checked against budgets before each request.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Local, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::Error;
+use crate::config::Price;
+use crate::openai::{Response, ResponseRequest, Usage};
+use crate::provider::{OnEvent, Provider, ProviderKind};
+
+/// Path to ledger, `cogni/ledger.ndjson` under `$XDG_STATE_HOME` or `~/.local/state`
+pub fn path() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+
+    Some(state_home.join("cogni").join("ledger.ndjson"))
+}
+
+/// Request recorded in ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: DateTime<Utc>,
+    pub provider: ProviderKind,
+    pub model: String,
+    /// Config profile that settings were resolved from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(flatten)]
+    pub usage: Usage,
+    /// Estimated cost in USD. `None` if config has no price for model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+}
+
+/// Append `entry` to ledger
+pub fn append(entry: &Entry) -> Result<(), Error> {
+    let path = path().ok_or_else(|| {
+        Error::IO(std::io::Error::other(
+            "neither XDG_STATE_HOME nor HOME is set",
+        ))
+    })?;
+    std::fs::create_dir_all(path.parent().expect("ledger path has parent"))?;
+
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+
+    // Single write in append mode, so concurrent invocations don't interleave lines
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(&line)?;
+    Ok(())
+}
+
+/// Entries in ledger, oldest first. Invalid lines are skipped
+pub fn read() -> Result<Vec<Entry>, Error> {
+    let Some(path) = path() else {
+        return Ok(vec![]);
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(Error::IO(e)),
+    };
+
+    let mut entries = vec![];
+    for (idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!(
+                "ignoring invalid ledger entry at {}:{} - {e}",
+                path.display(),
+                idx + 1
+            ),
+        }
+    }
+    Ok(entries)
+}
+
+/// Limits on estimated spend in USD, after which requests are refused
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Budget {
+    /// Limit for requests of a single invocation
+    pub invocation: Option<f64>,
+    /// Limit for requests since local midnight
+    pub daily: Option<f64>,
+    /// Limit for requests since start of local month
+    pub monthly: Option<f64>,
+}
+
+impl Budget {
+    /// Whether any limit is set
+    pub fn is_set(&self) -> bool {
+        self.invocation.is_some() || self.daily.is_some() || self.monthly.is_some()
+    }
+}
+
+/// Estimated spend in USD counted against `Budget`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Spend {
+    invocation: f64,
+    day: f64,
+    month: f64,
+}
+
+impl Spend {
+    /// Spend of `entries` on the day and month of `now`
+    fn from_entries(entries: &[Entry], now: DateTime<Local>) -> Self {
+        let mut spend = Spend::default();
+        for entry in entries {
+            let Some(cost) = entry.cost else {
+                continue;
+            };
+            let date = entry.timestamp.with_timezone(&Local).date_naive();
+            if (date.year(), date.month()) == (now.year(), now.month()) {
+                spend.month += cost;
+                if date == now.date_naive() {
+                    spend.day += cost;
+                }
+            }
+        }
+        spend
+    }
+
+    fn add(&mut self, cost: f64) {
+        self.invocation += cost;
+        self.day += cost;
+        self.month += cost;
+    }
+
+    /// Errors if spend, with `reserved` for requests in flight, has reached any limit of `budget`
+    fn check(&self, budget: &Budget, reserved: f64) -> Result<(), Error> {
+        let limits = [
+            (
+                budget.invocation,
+                self.invocation,
+                "--budget",
+                "by this invocation",
+            ),
+            (budget.daily, self.day, "daily_budget", "today"),
+            (budget.monthly, self.month, "monthly_budget", "this month"),
+        ];
+        for (limit, spent, name, period) in limits {
+            if let Some(limit) = limit
+                && spent + reserved >= limit
+            {
+                let in_flight = match reserved > 0.0 {
+                    true => format!(" with ${reserved:.4} reserved by requests in flight"),
+                    false => String::new(),
+                };
+                return Err(Error::BudgetExceeded(format!(
+                    "spent ${spent:.4} {period}{in_flight}, reaching {name} of ${limit:.2}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Spend of requests sent so far, and of requests still awaiting responses
+#[derive(Debug, Default)]
+struct Tally {
+    spend: Spend,
+    /// Number of requests sent without response yet
+    in_flight: u32,
+    /// Highest cost of a response so far, reserved for each request in flight
+    estimate: Option<f64>,
+}
+
+/// Keys that ledger entries are grouped by in reports
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    #[default]
+    Model,
+    Provider,
+    Profile,
+    /// Local date of request
+    Day,
+}
+
+/// Totals of ledger entries sharing a key
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    pub key: String,
+    pub requests: usize,
+    pub usage: Usage,
+    /// Estimated cost in USD of entries with prices. `None` if no entry has a price
+    pub cost: Option<f64>,
+}
+
+impl Summary {
+    fn add(&mut self, requests: usize, usage: &Usage, cost: Option<f64>) {
+        self.requests += requests;
+        self.usage += usage;
+        if let Some(cost) = cost {
+            *self.cost.get_or_insert(0.0) += cost;
+        }
+    }
+
+    /// Totals of `summaries`, with `key`
+    pub fn total(key: &str, summaries: &[Summary]) -> Summary {
+        let mut total = Summary {
+            key: key.to_string(),
+            ..Summary::default()
+        };
+        for summary in summaries {
+            total.add(summary.requests, &summary.usage, summary.cost);
+        }
+        total
+    }
+}
+
+/// Totals of `entries` at or after `since`, grouped per `group_by` and sorted by key
+pub fn summarize(
+    entries: &[Entry],
+    since: Option<DateTime<Utc>>,
+    group_by: GroupBy,
+) -> Vec<Summary> {
+    let mut groups: BTreeMap<String, Summary> = BTreeMap::new();
+    for entry in entries {
+        if since.is_some_and(|since| entry.timestamp < since) {
+            continue;
+        }
+        let key = match group_by {
+            GroupBy::Model => entry.model.clone(),
+            GroupBy::Provider => entry.provider.as_str().to_string(),
+            GroupBy::Profile => entry.profile.clone().unwrap_or_else(|| "-".to_string()),
+            GroupBy::Day => entry
+                .timestamp
+                .with_timezone(&Local)
+                .format("%Y-%m-%d")
+                .to_string(),
+        };
+        groups
+            .entry(key.clone())
+            .or_insert_with(|| Summary {
+                key,
+                ..Summary::default()
+            })
+            .add(1, &entry.usage, entry.cost);
+    }
+    groups.into_values().collect()
+}
+
+/// Provider recording requests sent via `inner` in ledger, and refusing requests once `budget` is
+/// reached.
+///
+/// Concurrent requests, e.g. from `cogni batch`, each reserve the highest cost of a response so far
+/// while in flight, so they can't all pass the budget check at once. Until the first response's cost
+/// is known, requests are sent one at a time. Spend may still exceed the budget by the difference
+/// between actual and reserved costs of requests in flight.
+pub(crate) struct LedgerProvider {
+    inner: Box<dyn Provider>,
+    kind: ProviderKind,
+    profile: Option<String>,
+    price: Option<Price>,
+    budget: Budget,
+    tally: Mutex<Tally>,
+    /// Notified as requests in flight finish
+    settled: Notify,
+}
+
+/// Request admitted by `LedgerProvider::reserve`, releasing its reservation once dropped
+struct Reservation<'a> {
+    provider: &'a LedgerProvider,
+    counted: bool,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if self.counted {
+            self.provider
+                .tally
+                .lock()
+                .expect("ledger lock poisoned")
+                .in_flight -= 1;
+            self.provider.settled.notify_waiters();
+        }
+    }
+}
+
+impl LedgerProvider {
+    pub(crate) fn new(
+        inner: Box<dyn Provider>,
+        kind: ProviderKind,
+        profile: Option<String>,
+        price: Option<Price>,
+        budget: Budget,
+    ) -> Result<Self, Error> {
+        // Spend of requests without price is unknown, so budget can't be enforced
+        if budget.is_set() && price.is_none() {
+            return Err(Error::InvalidConfig(
+                "budget is set, but [prices] has no entry for model".to_string(),
+            ));
+        }
+
+        // Ledger is only read when spend is needed, since it grows with every request
+        let spend = match budget.is_set() {
+            true => Spend::from_entries(&read()?, Local::now()),
+            false => Spend::default(),
+        };
+
+        Ok(Self {
+            inner,
+            kind,
+            profile,
+            price,
+            budget,
+            tally: Mutex::new(Tally {
+                spend,
+                ..Tally::default()
+            }),
+            settled: Notify::new(),
+        })
+    }
+
+    /// Wait until budget admits another request, with estimated cost reserved for requests in flight
+    async fn reserve(&self) -> Result<Reservation<'_>, Error> {
+        if !self.budget.is_set() {
+            return Ok(Reservation {
+                provider: self,
+                counted: false,
+            });
+        }
+
+        loop {
+            // Created before checking, so requests finishing in between aren't missed
+            let settled = self.settled.notified();
+            {
+                let mut tally = self.tally.lock().expect("ledger lock poisoned");
+                if tally.estimate.is_some() || tally.in_flight == 0 {
+                    let reserved = tally.estimate.unwrap_or_default() * f64::from(tally.in_flight);
+                    tally.spend.check(&self.budget, reserved)?;
+                    tally.in_flight += 1;
+                    return Ok(Reservation {
+                        provider: self,
+                        counted: true,
+                    });
+                }
+            }
+            settled.await;
+        }
+    }
+
+    /// Record `response` to `request` in ledger. Failures are logged, since the response is still
+    /// usable
+    fn record(&self, request: &ResponseRequest, response: &Response) {
+        let cost = self.price.as_ref().map(|price| price.cost(&response.usage));
+        if let Some(cost) = cost {
+            let mut tally = self.tally.lock().expect("ledger lock poisoned");
+            tally.spend.add(cost);
+            tally.estimate = Some(tally.estimate.map_or(cost, |estimate| estimate.max(cost)));
+        }
+
+        let entry = Entry {
+            timestamp: Utc::now(),
+            provider: self.kind,
+            model: request.model.clone(),
+            profile: self.profile.clone(),
+            usage: response.usage.clone(),
+            cost,
+        };
+        if let Err(e) = append(&entry) {
+            log::warn!("failed to record request in ledger - {e}");
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for LedgerProvider {
+    async fn create_response(&self, request: &ResponseRequest) -> Result<Response, Error> {
+        let _reservation = self.reserve().await?;
+        let response = self.inner.create_response(request).await?;
+        self.record(request, &response);
+        Ok(response)
+    }
+
+    async fn stream_response(
+        &self,
+        request: &ResponseRequest,
+        on_event: &mut OnEvent<'_>,
+    ) -> Result<Response, Error> {
+        let _reservation = self.reserve().await?;
+        let response = self.inner.stream_response(request, on_event).await?;
+        self.record(request, &response);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn entry(timestamp: DateTime<Local>, model: &str, cost: Option<f64>) -> Entry {
+        Entry {
+            timestamp: timestamp.with_timezone(&Utc),
+            provider: ProviderKind::OpenAI,
+            model: model.to_string(),
+            profile: None,
+            usage: Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+                total_tokens: 15,
+                ..Usage::default()
+            },
+            cost,
+        }
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, 0, 0)
+            .single()
+            .expect("valid local time")
+    }
+
+    #[test]
+    fn spend_of_day_and_month() {
+        let now = local(2026, 10, 17, 12);
+        let entries = [
+            entry(local(2026, 9, 30, 12), "gpt-5", Some(8.0)),
+            entry(local(2026, 10, 1, 12), "gpt-5", Some(4.0)),
+            entry(local(2026, 10, 17, 9), "gpt-5", Some(2.0)),
+            entry(local(2026, 10, 17, 10), "llama3", None),
+        ];
+
+        assert_eq!(
+            Spend::from_entries(&entries, now),
+            Spend {
+                invocation: 0.0,
+                day: 2.0,
+                month: 6.0,
+            }
+        );
+    }
+
+    #[test]
+    fn check_budget() {
+        let mut spend = Spend {
+            invocation: 0.0,
+            day: 2.0,
+            month: 6.0,
+        };
+        let budget = Budget {
+            invocation: Some(1.0),
+            daily: Some(5.0),
+            monthly: Some(10.0),
+        };
+        assert!(spend.check(&budget, 0.0).is_ok());
+
+        let err = spend.check(&budget, 2.5).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::BudgetExceeded(reason) if reason == "spent $0.0000 by this invocation with $2.5000 reserved by requests in flight, reaching --budget of $1.00"),
+            "unexpected error: {err:?}"
+        );
+
+        spend.add(1.0);
+        let err = spend.check(&budget, 0.0).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::BudgetExceeded(reason) if reason.contains("--budget of $1.00")),
+            "unexpected error: {err:?}"
+        );
+
+        let err = spend
+            .check(
+                &Budget {
+                    monthly: Some(7.0),
+                    ..Budget::default()
+                },
+                0.0,
+            )
+            .expect_err("should fail");
+        assert!(
+            matches!(&err, Error::BudgetExceeded(reason) if reason.contains("this month")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn summarize_by_model() {
+        let entries = [
+            entry(local(2026, 10, 1, 12), "gpt-5", Some(1.0)),
+            entry(local(2026, 10, 17, 9), "gpt-5", Some(2.0)),
+            entry(local(2026, 10, 17, 10), "llama3", None),
+        ];
+
+        let summaries = summarize(&entries, None, GroupBy::Model);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].key, "gpt-5");
+        assert_eq!(summaries[0].requests, 2);
+        assert_eq!(summaries[0].usage.total_tokens, 30);
+        assert_eq!(summaries[0].cost, Some(3.0));
+        assert_eq!(summaries[1].key, "llama3");
+        assert_eq!(summaries[1].cost, None);
+
+        let since = local(2026, 10, 17, 0).with_timezone(&Utc);
+        let summaries = summarize(&entries, Some(since), GroupBy::Day);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].key, "2026-10-17");
+        assert_eq!(summaries[0].requests, 2);
+        assert_eq!(summaries[0].cost, Some(2.0));
+    }
+
+    #[test]
+    fn entry_round_trip() -> Result<(), serde_json::Error> {
+        let entry = Entry {
+            profile: Some("work".to_string()),
+            ..entry(local(2026, 10, 17, 9), "gpt-5", Some(0.5))
+        };
+        let line = serde_json::to_string(&entry)?;
+        assert!(
+            line.contains("\"input_tokens\":10"),
+            "unexpected line: {line}"
+        );
+        assert_eq!(serde_json::from_str::<Entry>(&line)?, entry);
+        Ok(())
+    }
+}
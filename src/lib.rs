@@ -1,11 +1,22 @@
+pub mod attachment;
+pub mod cache;
+pub mod cassette;
 pub mod cli;
+pub mod config;
 pub mod error;
 pub mod exec;
+pub mod ledger;
 pub mod openai;
 pub mod parse;
+pub mod provider;
+pub mod retry;
+pub mod schema;
+pub mod session;
+pub mod template;
+pub mod tools;
 
-pub use error::Error;
+pub use error::{Error, ErrorKind, ErrorReport};
 pub use exec::exec;
-pub use parse::parse_messages;
+pub use parse::{parse_messages, parse_messages_with};
 
 pub type Result<T> = std::result::Result<T, Error>;
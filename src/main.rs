@@ -1,9 +1,34 @@
+use std::process::ExitCode;
+
 use anyhow::Result;
-use cogni::cli;
+use clap::ArgMatches;
+use cogni::ErrorReport;
+use cogni::cli::{self, ErrorFormat};
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let invocation = cli::parse();
+async fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let matches = cli::matches();
+    let error_format = cli::error_format(&matches);
+
+    match run(matches).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let report = ErrorReport::new(&e);
+            match error_format {
+                ErrorFormat::Text => eprintln!("Error: {e:?}"),
+                ErrorFormat::JSON => eprintln!(
+                    "{}",
+                    serde_json::to_string(&report).expect("failed to serialize error")
+                ),
+            }
+            ExitCode::from(report.exit_code())
+        }
+    }
+}
+
+async fn run(matches: ArgMatches) -> Result<()> {
+    let invocation = cli::parse(matches)?;
     cogni::exec(invocation).await?;
     Ok(())
 }
@@ -4,6 +4,9 @@ use std::convert::TryFrom;
 use std::time::Duration;
 
 use crate::Error;
+use crate::attachment::{Attachment, AttachmentKind};
+use crate::cassette::Cassette;
+use crate::retry::{self, RetryPolicy};
 use chrono::serde::ts_seconds;
 use chrono::{DateTime, Utc};
 use derive_builder::Builder;
@@ -19,30 +22,65 @@ pub struct Client {
     api_key: Option<String>,
     /// Base URL for API Endpoint
     base_url: String,
+    /// Policy for retrying transient failures
+    retry_policy: RetryPolicy,
+    /// Cassette that interactions are recorded to or replayed from
+    cassette: Option<Cassette>,
 }
 
 /// Requests for the Responses API
 /// Reference: <https://platform.openai.com/docs/api-reference/responses>
 #[derive(Builder, Default)]
 pub struct ResponseRequest {
-    model: String,
-    messages: Vec<Message>,
+    pub(crate) model: String,
+    pub(crate) messages: Vec<Message>,
+    #[builder(default)]
+    pub(crate) temperature: Option<f32>,
+    pub(crate) timeout: Duration,
+    #[builder(default)]
+    pub(crate) reasoning: Option<Reasoning>,
+    #[builder(default)]
+    pub(crate) text_format: Option<TextFormat>,
+    #[builder(default)]
+    pub(crate) tools: Vec<Tool>,
+    /// Id of stored response that this request continues
+    #[builder(default)]
+    pub(crate) previous_response_id: Option<String>,
+    /// Whether response is stored for later retrieval or continuation
     #[builder(default)]
-    temperature: Option<f32>,
-    timeout: Duration,
+    pub(crate) store: Option<bool>,
+    /// Upper bound for tokens generated, including reasoning tokens
     #[builder(default)]
-    reasoning: Option<Reasoning>,
+    pub(crate) max_output_tokens: Option<u32>,
 }
 
 /// Responses from the Responses API
 /// Reference: <https://platform.openai.com/docs/api-reference/responses>
-#[derive(Builder, Default, Debug, Serialize, Deserialize)]
+#[derive(Builder, Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Response {
+    /// Id of response, for continuing from it via `previous_response_id`
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     #[serde(with = "ts_seconds")]
     pub created: DateTime<Utc>,
     pub choices: Vec<Choice>,
     pub model: String,
     pub usage: Usage,
+    /// Whether response was served from cache rather than created by provider
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+}
+
+/// Events surfaced while streaming a response
+/// Reference: <https://platform.openai.com/docs/api-reference/responses-streaming>
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// Incremental text for the output message
+    OutputTextDelta(String),
+    /// Final response, including usage
+    Completed(Response),
 }
 
 /// API Errors from OpenAI
@@ -53,12 +91,15 @@ pub struct APIError {
     pub error_type: String,
     pub param: Option<String>,
     pub code: Option<String>,
+    /// HTTP status of response carrying error. `None` for errors within streams
+    #[serde(skip)]
+    pub status: Option<u16>,
 }
 
 /// Wraps `APIError` for deserializing OpenAI Response
 #[derive(Debug, Deserialize)]
-struct APIErrorContainer {
-    error: APIError,
+pub(crate) struct APIErrorContainer {
+    pub(crate) error: APIError,
 }
 
 /// Messages in Responses API request and response
@@ -66,6 +107,15 @@ struct APIErrorContainer {
 pub struct Message {
     pub role: Role,
     pub content: String,
+    /// Tools called by assistant
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Call that `Role::Tool` message is the output of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Images and files following content
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
 }
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
@@ -74,6 +124,30 @@ pub enum Role {
     System,
     Assistant,
     User,
+    Tool,
+}
+
+/// Function tools that model may call
+/// Reference: <https://platform.openai.com/docs/guides/function-calling>
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Tool {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON Schema for arguments
+    #[serde(default = "Tool::default_parameters")]
+    pub parameters: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// Call to a function tool requested by model
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolCall {
+    pub call_id: String,
+    pub name: String,
+    /// Arguments as JSON encoded string
+    pub arguments: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
@@ -81,6 +155,26 @@ pub struct Usage {
     pub input_tokens: u32,
     pub output_tokens: u32,
     pub total_tokens: u32,
+    /// Input tokens served from provider's prompt cache, included in `input_tokens`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cached_tokens: u32,
+    /// Output tokens spent on reasoning, included in `output_tokens`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub reasoning_tokens: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.reasoning_tokens += other.reasoning_tokens;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -102,6 +196,21 @@ pub enum ReasoningEffort {
     High,
 }
 
+/// Format that text output must adhere to
+/// Reference: <https://platform.openai.com/docs/guides/structured-outputs>
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextFormat {
+    /// Output is a JSON object
+    JsonObject,
+    /// Output is JSON adhering to `schema`
+    JsonSchema {
+        name: String,
+        schema: Value,
+        strict: bool,
+    },
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum FinishReason {
@@ -119,44 +228,142 @@ pub struct Choice {
 
 impl Client {
     pub fn new(api_key: Option<String>, base_url: String) -> Result<Self, Error> {
-        let client = reqwest::Client::builder()
-            .build()
-            .map_err(Error::FailedToFetch)?;
+        let client = reqwest::Client::builder().build().map_err(Error::from)?;
         Ok(Self {
             client,
             api_key,
             base_url,
+            retry_policy: RetryPolicy::none(),
+            cassette: None,
         })
     }
 
+    /// Set policy for retrying rate limited, server error, and connection failures
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set cassette that interactions are recorded to, or replayed from without network access
+    pub fn with_cassette(mut self, cassette: Option<Cassette>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
     pub async fn create_response(&self, request: &ResponseRequest) -> Result<Response, Error> {
+        let payload = request.to_payload();
+        let body = match self.replay(&payload)? {
+            Some(body) => body,
+            None => {
+                let resp = self.send(&payload, request.timeout).await?;
+                let body = resp.text().await.map_err(Error::from)?;
+                self.record(&payload, StatusCode::OK, &body)?;
+                body
+            }
+        };
+
+        let responses: ResponsesAPIResponse = serde_json::from_str(&body)?;
+        Response::try_from(responses).map_err(Error::UnexpectedResponse)
+    }
+
+    /// Create a response with `"stream": true`, invoking `on_event` as server-sent events arrive.
+    /// Returns the final response once the stream completes.
+    pub async fn stream_response(
+        &self,
+        request: &ResponseRequest,
+        mut on_event: impl FnMut(&StreamEvent) -> Result<(), Error>,
+    ) -> Result<Response, Error> {
+        let mut payload = request.to_payload();
+        if let Some(obj) = payload.as_object_mut() {
+            obj.insert("stream".to_string(), json!(true));
+        }
+
+        let mut buffer = SseBuffer::default();
+        let mut handle_chunk = |chunk: &[u8]| -> Result<Option<Response>, Error> {
+            buffer.push(chunk);
+            while let Some(data) = buffer.next_data() {
+                let event = serde_json::from_str::<ResponsesStreamEvent>(&data)?;
+                if let Some(response) = handle_stream_event(event, &mut on_event)? {
+                    return Ok(Some(response));
+                }
+            }
+            Ok(None)
+        };
+        let incomplete_stream =
+            || Error::UnexpectedResponse("stream ended before response completed".to_string());
+
+        if let Some(body) = self.replay(&payload)? {
+            return handle_chunk(body.as_bytes())?.ok_or_else(incomplete_stream);
+        }
+
+        let mut resp = self.send(&payload, request.timeout).await?;
+        let mut body = vec![];
+
+        let res = loop {
+            let Some(chunk) = resp.chunk().await.map_err(Error::from)? else {
+                break Err(incomplete_stream());
+            };
+            body.extend_from_slice(&chunk);
+            match handle_chunk(&chunk) {
+                Ok(None) => continue,
+                Ok(Some(response)) => break Ok(response),
+                Err(e) => break Err(e),
+            }
+        };
+
+        // Events up to the end of response are recorded, including failures reported in stream
+        self.record(&payload, StatusCode::OK, &String::from_utf8_lossy(&body))?;
+        res
+    }
+
+    /// Body of response recorded for `payload`, when replaying from cassette. Recorded error
+    /// responses are returned as errors
+    fn replay(&self, payload: &Value) -> Result<Option<String>, Error> {
+        let Some(cassette) = &self.cassette else {
+            return Ok(None);
+        };
+        let Some(interaction) = cassette.find(payload)? else {
+            return Ok(None);
+        };
+
+        match StatusCode::from_u16(interaction.status) {
+            Ok(StatusCode::OK) => Ok(Some(interaction.body.clone())),
+            Ok(status) => Err(api_error(status, &interaction.body)),
+            Err(_) => Err(Error::InvalidCassette(format!(
+                "invalid status {}",
+                interaction.status
+            ))),
+        }
+    }
+
+    /// Append response to cassette, when recording
+    fn record(&self, payload: &Value, status: StatusCode, body: &str) -> Result<(), Error> {
+        match &self.cassette {
+            Some(cassette) => cassette.append(payload, status.as_u16(), body),
+            None => Ok(()),
+        }
+    }
+
+    /// Send payload to the Responses API with retries, mapping non-200 statuses to `Error::OpenAIError`
+    async fn send(&self, payload: &Value, timeout: Duration) -> Result<reqwest::Response, Error> {
         let api_key = &self.api_key.as_ref().ok_or(Error::NoAPIKey)?;
 
-        let resp = self
-            .client
-            .post(self.responses_endpoint())
-            .bearer_auth(api_key)
-            .timeout(request.timeout)
-            .header("Content-Type", "application/json")
-            .json(&request.to_payload())
-            .send()
-            .await
-            .map_err(Error::FailedToFetch)?;
+        let (resp, attempts) = retry::send(&self.retry_policy, || {
+            self.client
+                .post(self.responses_endpoint())
+                .bearer_auth(api_key)
+                .timeout(timeout)
+                .header("Content-Type", "application/json")
+                .json(payload)
+        })
+        .await?;
 
         match resp.status() {
-            StatusCode::OK => {
-                let responses: ResponsesAPIResponse =
-                    resp.json().await.map_err(Error::FailedToFetch)?;
-                let response = Response::try_from(responses).map_err(Error::UnexpectedResponse)?;
-                Ok(response)
-            }
-            _ => {
-                let error = resp
-                    .json::<APIErrorContainer>()
-                    .await
-                    .map_err(Error::FailedToFetch)?
-                    .error;
-                Err(Error::OpenAIError { error })
+            StatusCode::OK => Ok(resp),
+            status => {
+                let body = resp.text().await.map_err(Error::from)?;
+                self.record(payload, status, &body)?;
+                Err(retry::exhausted(api_error(status, &body), attempts))
             }
         }
     }
@@ -166,45 +373,122 @@ impl Client {
     }
 }
 
+/// Error for response with non-200 `status` and `body`
+fn api_error(status: StatusCode, body: &str) -> Error {
+    match serde_json::from_str::<APIErrorContainer>(body) {
+        Ok(container) => Error::api(status.as_u16(), container.error),
+        Err(_) => Error::UnexpectedResponse(format!("{status} - {body}")),
+    }
+}
+
 impl Message {
-    pub fn system(content: &str) -> Message {
+    pub fn new(role: Role, content: &str) -> Message {
         Message {
-            role: Role::System,
+            role,
             content: content.to_string(),
+            tool_calls: vec![],
+            tool_call_id: None,
+            attachments: vec![],
         }
     }
+    pub fn system(content: &str) -> Message {
+        Message::new(Role::System, content)
+    }
     pub fn user(content: &str) -> Message {
+        Message::new(Role::User, content)
+    }
+    pub fn assistant(content: &str) -> Message {
+        Message::new(Role::Assistant, content)
+    }
+    /// Assistant message calling tools
+    pub fn tool_calls(tool_calls: Vec<ToolCall>) -> Message {
         Message {
-            role: Role::User,
-            content: content.to_string(),
+            tool_calls,
+            ..Message::assistant("")
         }
     }
-    pub fn assistant(content: &str) -> Message {
+    /// Output of tool call with `call_id`
+    pub fn tool(call_id: &str, content: &str) -> Message {
         Message {
-            role: Role::Assistant,
-            content: content.to_string(),
+            tool_call_id: Some(call_id.to_string()),
+            ..Message::new(Role::Tool, content)
         }
     }
 }
 
 impl Message {
-    fn to_responses_input(&self) -> serde_json::Value {
-        json!({
-            "role": self.role.as_str(),
-            "content": [{
-                "type": "input_text",
-                "text": self.content.clone(),
-            }]
-        })
+    /// Input items for message. Tool calls and outputs are separate items from message content
+    fn to_responses_input(&self) -> Vec<Value> {
+        if self.role == Role::Tool {
+            return vec![json!({
+                "type": "function_call_output",
+                "call_id": self.tool_call_id.clone().unwrap_or_default(),
+                "output": self.content.clone(),
+            })];
+        }
+
+        let mut items = vec![];
+
+        if !self.content.is_empty() || self.tool_calls.is_empty() {
+            let text = (!self.content.is_empty() || self.attachments.is_empty()).then(|| {
+                json!({
+                    "type": "input_text",
+                    "text": self.content.clone(),
+                })
+            });
+            let attachments = self.attachments.iter().map(|a| match a.kind {
+                AttachmentKind::Image => json!({
+                    "type": "input_image",
+                    "image_url": a.url,
+                }),
+                AttachmentKind::File => json!({
+                    "type": "input_file",
+                    "filename": a.filename(),
+                    "file_data": a.url,
+                }),
+            });
+            items.push(json!({
+                "role": self.role.as_str(),
+                "content": text.into_iter().chain(attachments).collect::<Vec<_>>(),
+            }));
+        }
+
+        items.extend(self.tool_calls.iter().map(|call| {
+            json!({
+                "type": "function_call",
+                "call_id": call.call_id,
+                "name": call.name,
+                "arguments": call.arguments,
+            })
+        }));
+
+        items
+    }
+}
+
+impl Tool {
+    fn default_parameters() -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn to_responses_tool(&self) -> Value {
+        let mut tool = json!({ "type": "function" });
+        if let (Some(obj), Ok(Value::Object(fields))) =
+            (tool.as_object_mut(), serde_json::to_value(self))
+        {
+            obj.extend(fields);
+        }
+        tool
     }
 }
 
 impl Role {
-    fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             Role::System => "system",
             Role::Assistant => "assistant",
             Role::User => "user",
+            Role::Tool => "tool",
         }
     }
 }
@@ -214,11 +498,11 @@ impl ResponseRequest {
         ResponseRequestBuilder::default()
     }
 
-    fn to_payload(&self) -> Value {
+    pub(crate) fn to_payload(&self) -> Value {
         let input = self
             .messages
             .iter()
-            .map(Message::to_responses_input)
+            .flat_map(Message::to_responses_input)
             .collect::<Vec<_>>();
 
         let mut payload = json!({
@@ -241,6 +525,37 @@ impl ResponseRequest {
             );
         }
 
+        if let Some(format) = &self.text_format
+            && let Some(obj) = payload.as_object_mut()
+        {
+            obj.insert("text".to_string(), json!({ "format": format }));
+        }
+
+        if !self.tools.is_empty()
+            && let Some(obj) = payload.as_object_mut()
+        {
+            let tools = self.tools.iter().map(Tool::to_responses_tool);
+            obj.insert("tools".to_string(), Value::Array(tools.collect()));
+        }
+
+        if let Some(max_output_tokens) = self.max_output_tokens
+            && let Some(obj) = payload.as_object_mut()
+        {
+            obj.insert("max_output_tokens".to_string(), json!(max_output_tokens));
+        }
+
+        if let Some(id) = &self.previous_response_id
+            && let Some(obj) = payload.as_object_mut()
+        {
+            obj.insert("previous_response_id".to_string(), json!(id));
+        }
+
+        if let Some(store) = self.store
+            && let Some(obj) = payload.as_object_mut()
+        {
+            obj.insert("store".to_string(), json!(store));
+        }
+
         payload
     }
 }
@@ -249,10 +564,19 @@ impl Response {
     pub fn builder() -> ResponseBuilder {
         ResponseBuilder::default()
     }
+
+    /// Tools called across choices in response
+    pub fn tool_calls(&self) -> impl Iterator<Item = &ToolCall> {
+        self.choices
+            .iter()
+            .flat_map(|c| c.message.tool_calls.iter())
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct ResponsesAPIResponse {
+    #[serde(default)]
+    id: Option<String>,
     #[serde(rename = "created_at", alias = "created")]
     #[serde(with = "ts_seconds")]
     created: DateTime<Utc>,
@@ -260,6 +584,126 @@ struct ResponsesAPIResponse {
     #[serde(default)]
     output: Vec<ResponseOutput>,
     usage: ResponsesUsage,
+    /// `completed`, or `incomplete` if generation stopped early
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    incomplete_details: Option<IncompleteDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IncompleteDetails {
+    reason: Option<String>,
+}
+
+/// Server-sent events from the Responses API with `"stream": true`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ResponsesStreamEvent {
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta { delta: String },
+    #[serde(rename = "response.completed", alias = "response.incomplete")]
+    Completed { response: ResponsesAPIResponse },
+    #[serde(rename = "response.failed")]
+    Failed { response: FailedResponse },
+    #[serde(rename = "error")]
+    Error {
+        message: String,
+        code: Option<String>,
+        param: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct FailedResponse {
+    error: Option<FailedResponseError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FailedResponseError {
+    code: Option<String>,
+    message: String,
+}
+
+/// Handle a single stream event. Returns final response on completion
+fn handle_stream_event(
+    event: ResponsesStreamEvent,
+    on_event: &mut impl FnMut(&StreamEvent) -> Result<(), Error>,
+) -> Result<Option<Response>, Error> {
+    match event {
+        ResponsesStreamEvent::OutputTextDelta { delta } => {
+            on_event(&StreamEvent::OutputTextDelta(delta))?;
+            Ok(None)
+        }
+        ResponsesStreamEvent::Completed { response } => {
+            let response = Response::try_from(response).map_err(Error::UnexpectedResponse)?;
+            on_event(&StreamEvent::Completed(response.clone()))?;
+            Ok(Some(response))
+        }
+        ResponsesStreamEvent::Failed { response } => {
+            let (message, code) = match response.error {
+                Some(e) => (e.message, e.code),
+                None => ("response failed".to_string(), None),
+            };
+            Err(Error::OpenAIError {
+                error: APIError {
+                    message,
+                    error_type: "response_failed".to_string(),
+                    param: None,
+                    code,
+                    status: None,
+                },
+            })
+        }
+        ResponsesStreamEvent::Error {
+            message,
+            code,
+            param,
+        } => Err(Error::OpenAIError {
+            error: APIError {
+                message,
+                error_type: "stream_error".to_string(),
+                param,
+                code,
+                status: None,
+            },
+        }),
+        ResponsesStreamEvent::Other => Ok(None),
+    }
+}
+
+/// Buffers bytes of a server-sent event stream, yielding `data` payloads of complete events
+#[derive(Debug, Default)]
+pub(crate) struct SseBuffer {
+    buf: Vec<u8>,
+}
+
+impl SseBuffer {
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        // Chunks may split multi-byte characters, so bytes are only decoded per complete event
+        self.buf.extend(chunk.iter().filter(|&&b| b != b'\r'));
+    }
+
+    pub(crate) fn next_data(&mut self) -> Option<String> {
+        loop {
+            let end = self.buf.windows(2).position(|w| w == b"\n\n")?;
+            let event = String::from_utf8_lossy(&self.buf[..end]).into_owned();
+            self.buf.drain(..end + 2);
+
+            let data = event
+                .lines()
+                .filter_map(|l| l.strip_prefix("data:"))
+                .map(|l| l.strip_prefix(' ').unwrap_or(l))
+                .collect::<Vec<_>>();
+
+            if data.is_empty() {
+                continue;
+            }
+            return Some(data.join("\n"));
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -270,6 +714,34 @@ struct ResponsesUsage {
     output_tokens: u32,
     #[serde(default)]
     total_tokens: u32,
+    #[serde(default)]
+    input_tokens_details: InputTokensDetails,
+    #[serde(default)]
+    output_tokens_details: OutputTokensDetails,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct InputTokensDetails {
+    #[serde(default)]
+    pub(crate) cached_tokens: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct OutputTokensDetails {
+    #[serde(default)]
+    pub(crate) reasoning_tokens: u32,
+}
+
+impl From<ResponsesUsage> for Usage {
+    fn from(usage: ResponsesUsage) -> Self {
+        Usage {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            total_tokens: usage.total_tokens,
+            cached_tokens: usage.input_tokens_details.cached_tokens,
+            reasoning_tokens: usage.output_tokens_details.reasoning_tokens,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -279,6 +751,9 @@ struct ResponseOutput {
     role: Option<Role>,
     #[serde(default)]
     content: Vec<ResponseContent>,
+    call_id: Option<String>,
+    name: Option<String>,
+    arguments: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -322,30 +797,84 @@ impl ResponseOutput {
     }
 }
 
+impl ResponsesAPIResponse {
+    /// Finish reason for response with `incomplete` status
+    fn incomplete_finish_reason(&self) -> Option<FinishReason> {
+        if self.status.as_deref() != Some("incomplete") {
+            return None;
+        }
+        let reason = self
+            .incomplete_details
+            .as_ref()
+            .and_then(|d| d.reason.as_deref());
+        match reason {
+            Some("content_filter") => Some(FinishReason::ContentFilter),
+            _ => Some(FinishReason::Length),
+        }
+    }
+}
+
 impl TryFrom<ResponsesAPIResponse> for Response {
     type Error = String;
 
     fn try_from(value: ResponsesAPIResponse) -> Result<Self, Self::Error> {
         let mut choices = Vec::new();
+        let mut tool_calls = Vec::new();
+        let incomplete = value.incomplete_finish_reason();
 
         for output in value.output.into_iter() {
-            if output.item_type != "message" {
-                continue;
+            match output.item_type.as_str() {
+                "message" => {
+                    let text = output
+                        .aggregated_text()
+                        .ok_or_else(|| "response message missing text content".to_string())?;
+
+                    let message = Message::new(output.role.unwrap_or(Role::Assistant), &text);
+
+                    choices.push(Choice {
+                        message,
+                        finish_reason: FinishReason::Stop,
+                    });
+                }
+                "function_call" => {
+                    let (Some(call_id), Some(name)) = (output.call_id, output.name) else {
+                        return Err("function call missing call_id or name".to_string());
+                    };
+                    tool_calls.push(ToolCall {
+                        call_id,
+                        name,
+                        arguments: output.arguments.unwrap_or_default(),
+                    });
+                }
+                _ => continue,
             }
+        }
 
-            let text = output
-                .aggregated_text()
-                .ok_or_else(|| "response message missing text content".to_string())?;
-
-            let message = Message {
-                role: output.role.unwrap_or(Role::Assistant),
-                content: text,
-            };
+        // Function calls are attached to the assistant message preceding them
+        if !tool_calls.is_empty() {
+            match choices.last_mut() {
+                Some(choice) => {
+                    choice.message.tool_calls = tool_calls;
+                    choice.finish_reason = FinishReason::FunctionCall;
+                }
+                None => choices.push(Choice {
+                    message: Message::tool_calls(tool_calls),
+                    finish_reason: FinishReason::FunctionCall,
+                }),
+            }
+        }
 
-            choices.push(Choice {
-                message,
-                finish_reason: FinishReason::Stop,
-            });
+        // Truncated responses may have no message, e.g. if all tokens were spent on reasoning
+        if let Some(reason) = incomplete {
+            if choices.is_empty() {
+                choices.push(Choice {
+                    message: Message::assistant(""),
+                    finish_reason: reason.clone(),
+                });
+            }
+            for choice in choices.iter_mut() {
+                choice.finish_reason = reason.clone();
+            }
         }
 
         if choices.is_empty() {
@@ -353,14 +882,12 @@ impl TryFrom<ResponsesAPIResponse> for Response {
         }
 
         Ok(Response {
+            id: value.id,
             created: value.created,
             choices,
             model: value.model,
-            usage: Usage {
-                input_tokens: value.usage.input_tokens,
-                output_tokens: value.usage.output_tokens,
-                total_tokens: value.usage.total_tokens,
-            },
+            usage: value.usage.into(),
+            cached: false,
         })
     }
 }
@@ -389,7 +916,9 @@ mod test {
              }],
              "usage": {
                  "input_tokens": 8,
+                 "input_tokens_details": { "cached_tokens": 4 },
                  "output_tokens": 9,
+                 "output_tokens_details": { "reasoning_tokens": 2 },
                  "total_tokens": 17
              }
         }
@@ -402,10 +931,7 @@ mod test {
         assert_eq!(
             resp.choices,
             vec![Choice {
-                message: Message {
-                    role: Role::Assistant,
-                    content: "Hello! How can I assist you today?".to_string()
-                },
+                message: Message::new(Role::Assistant, "Hello! How can I assist you today?"),
                 finish_reason: FinishReason::Stop
             }]
         );
@@ -416,6 +942,8 @@ mod test {
                 input_tokens: 8,
                 output_tokens: 9,
                 total_tokens: 17,
+                cached_tokens: 4,
+                reasoning_tokens: 2,
             }
         );
 
@@ -458,6 +986,170 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn response_payload_includes_text_format() -> Result<()> {
+        let request = ResponseRequest::builder()
+            .model("gpt-5".to_string())
+            .messages(vec![Message::user("Hello")])
+            .timeout(Duration::from_secs(30))
+            .text_format(Some(TextFormat::JsonSchema {
+                name: "event".to_string(),
+                schema: json!({"type": "object"}),
+                strict: true,
+            }))
+            .build()
+            .expect("request builds");
+
+        let payload = request.to_payload();
+
+        assert_eq!(
+            payload["text"],
+            json!({
+                "format": {
+                    "type": "json_schema",
+                    "name": "event",
+                    "schema": {"type": "object"},
+                    "strict": true,
+                }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn response_payload_includes_previous_response_id() -> Result<()> {
+        let request = ResponseRequest::builder()
+            .model("gpt-5".to_string())
+            .messages(vec![Message::user("And then?")])
+            .timeout(Duration::from_secs(30))
+            .previous_response_id(Some("resp_123".to_string()))
+            .store(Some(true))
+            .build()
+            .expect("request builds");
+
+        let payload = request.to_payload();
+
+        assert_eq!(payload["previous_response_id"], "resp_123");
+        assert_eq!(payload["store"], true);
+        Ok(())
+    }
+
+    #[test]
+    fn response_payload_omits_previous_response_id_when_not_set() -> Result<()> {
+        let request = ResponseRequest::builder()
+            .model("gpt-5".to_string())
+            .messages(vec![Message::user("Hello")])
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("request builds");
+
+        let payload = request.to_payload();
+
+        assert!(payload.get("previous_response_id").is_none());
+        assert!(payload.get("store").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_response_id() -> Result<()> {
+        let data = r#"{
+             "id": "resp_123",
+             "created_at": 1688413145,
+             "model": "gpt-5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "Hello" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#;
+
+        let resp = serde_json::from_str::<ResponsesAPIResponse>(data)?;
+        let resp = Response::try_from(resp).map_err(|e| anyhow::anyhow!(e))?;
+
+        assert_eq!(resp.id.as_deref(), Some("resp_123"));
+        assert_eq!(serde_json::to_value(&resp)?["id"], "resp_123");
+        Ok(())
+    }
+
+    #[test]
+    fn response_payload_includes_max_output_tokens() -> Result<()> {
+        let request = ResponseRequest::builder()
+            .model("gpt-5".to_string())
+            .messages(vec![Message::user("Hello")])
+            .timeout(Duration::from_secs(30))
+            .max_output_tokens(Some(256))
+            .build()
+            .expect("request builds");
+
+        let payload = request.to_payload();
+
+        assert_eq!(payload["max_output_tokens"], 256);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_incomplete_response() -> Result<()> {
+        let data = r#"{
+             "created_at": 1688413145,
+             "model": "gpt-5",
+             "status": "incomplete",
+             "incomplete_details": { "reason": "max_output_tokens" },
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "Once upon a" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 4, "total_tokens": 12 }
+        }"#;
+
+        let resp = serde_json::from_str::<ResponsesAPIResponse>(data)?;
+        let resp = Response::try_from(resp).map_err(|e| anyhow::anyhow!(e))?;
+
+        assert_eq!(resp.choices[0].message.content, "Once upon a");
+        assert_eq!(resp.choices[0].finish_reason, FinishReason::Length);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_incomplete_response_without_message() -> Result<()> {
+        let data = r#"{
+             "created_at": 1688413145,
+             "model": "gpt-5",
+             "status": "incomplete",
+             "incomplete_details": { "reason": "content_filter" },
+             "output": [{ "type": "reasoning", "summary": [] }],
+             "usage": { "input_tokens": 8, "output_tokens": 4, "total_tokens": 12 }
+        }"#;
+
+        let resp = serde_json::from_str::<ResponsesAPIResponse>(data)?;
+        let resp = Response::try_from(resp).map_err(|e| anyhow::anyhow!(e))?;
+
+        assert_eq!(resp.choices.len(), 1);
+        assert_eq!(resp.choices[0].message, Message::assistant(""));
+        assert_eq!(resp.choices[0].finish_reason, FinishReason::ContentFilter);
+        Ok(())
+    }
+
+    #[test]
+    fn response_payload_includes_json_object_format() -> Result<()> {
+        let request = ResponseRequest::builder()
+            .model("gpt-5".to_string())
+            .messages(vec![Message::user("Hello")])
+            .timeout(Duration::from_secs(30))
+            .text_format(Some(TextFormat::JsonObject))
+            .build()
+            .expect("request builds");
+
+        let payload = request.to_payload();
+
+        assert_eq!(
+            payload["text"],
+            json!({ "format": { "type": "json_object" } })
+        );
+        Ok(())
+    }
+
     #[test]
     fn response_try_from_concatenates_segments() -> Result<()> {
         let data = r#"{
@@ -491,6 +1183,159 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn responses_input_with_attachments() {
+        let message = Message {
+            attachments: vec![
+                Attachment {
+                    kind: AttachmentKind::Image,
+                    source: "https://example.com/cat.jpg".to_string(),
+                    url: "https://example.com/cat.jpg".to_string(),
+                },
+                Attachment {
+                    kind: AttachmentKind::File,
+                    source: "docs/report.pdf".to_string(),
+                    url: "data:application/pdf;base64,aGk=".to_string(),
+                },
+            ],
+            ..Message::user("Describe these")
+        };
+
+        assert_eq!(
+            message.to_responses_input(),
+            vec![json!({
+                "role": "user",
+                "content": [
+                    {"type": "input_text", "text": "Describe these"},
+                    {"type": "input_image", "image_url": "https://example.com/cat.jpg"},
+                    {
+                        "type": "input_file",
+                        "filename": "report.pdf",
+                        "file_data": "data:application/pdf;base64,aGk=",
+                    },
+                ],
+            })]
+        );
+
+        let image_only = Message {
+            content: String::new(),
+            attachments: message.attachments[..1].to_vec(),
+            ..message
+        };
+        assert_eq!(
+            image_only.to_responses_input()[0]["content"],
+            json!([{"type": "input_image", "image_url": "https://example.com/cat.jpg"}])
+        );
+    }
+
+    #[test]
+    fn response_try_from_function_calls() -> Result<()> {
+        let data = r#"{
+             "created": 1688413145,
+             "model": "gpt-5",
+             "output": [{
+                 "id": "fc_1",
+                 "type": "function_call",
+                 "status": "completed",
+                 "call_id": "call_1",
+                 "name": "get_weather",
+                 "arguments": "{\"city\":\"Tokyo\"}"
+             }, {
+                 "id": "fc_2",
+                 "type": "function_call",
+                 "status": "completed",
+                 "call_id": "call_2",
+                 "name": "get_time",
+                 "arguments": "{}"
+             }],
+             "usage": {
+                 "input_tokens": 1,
+                 "output_tokens": 1,
+                 "total_tokens": 2
+             }
+        }
+        "#;
+
+        let resp = serde_json::from_str::<ResponsesAPIResponse>(data)?;
+        let resp = Response::try_from(resp).map_err(|e| anyhow::anyhow!(e))?;
+
+        let calls = vec![
+            ToolCall {
+                call_id: "call_1".to_string(),
+                name: "get_weather".to_string(),
+                arguments: r#"{"city":"Tokyo"}"#.to_string(),
+            },
+            ToolCall {
+                call_id: "call_2".to_string(),
+                name: "get_time".to_string(),
+                arguments: "{}".to_string(),
+            },
+        ];
+        assert_eq!(
+            resp.choices,
+            vec![Choice {
+                message: Message::tool_calls(calls.clone()),
+                finish_reason: FinishReason::FunctionCall,
+            }]
+        );
+        assert_eq!(resp.tool_calls().cloned().collect::<Vec<_>>(), calls);
+        Ok(())
+    }
+
+    #[test]
+    fn response_payload_includes_tools_and_tool_messages() -> Result<()> {
+        let call = ToolCall {
+            call_id: "call_1".to_string(),
+            name: "get_weather".to_string(),
+            arguments: r#"{"city":"Tokyo"}"#.to_string(),
+        };
+        let request = ResponseRequest::builder()
+            .model("gpt-5".to_string())
+            .messages(vec![
+                Message::user("Weather?"),
+                Message::tool_calls(vec![call]),
+                Message::tool("call_1", "Sunny"),
+            ])
+            .timeout(Duration::from_secs(30))
+            .tools(vec![Tool {
+                name: "get_weather".to_string(),
+                description: Some("Gets weather".to_string()),
+                parameters: json!({"type": "object"}),
+                strict: None,
+            }])
+            .build()
+            .expect("request builds");
+
+        let payload = request.to_payload();
+
+        assert_eq!(
+            payload["tools"],
+            json!([{
+                "type": "function",
+                "name": "get_weather",
+                "description": "Gets weather",
+                "parameters": {"type": "object"},
+            }])
+        );
+        assert_eq!(
+            payload["input"],
+            json!([{
+                "role": "user",
+                "content": [{"type": "input_text", "text": "Weather?"}],
+            }, {
+                "type": "function_call",
+                "call_id": "call_1",
+                "name": "get_weather",
+                "arguments": "{\"city\":\"Tokyo\"}",
+            }, {
+                "type": "function_call_output",
+                "call_id": "call_1",
+                "output": "Sunny",
+            }])
+        );
+        Ok(())
+    }
+
     #[test]
     fn response_try_from_errors_without_assistant_message() -> Result<()> {
         let data = r#"{
@@ -523,6 +1368,94 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn sse_buffer_yields_complete_events() {
+        let mut buffer = SseBuffer::default();
+
+        buffer.push(b"event: response.output_text.delta\ndata: {\"a\":");
+        assert_eq!(buffer.next_data(), None, "incomplete event is buffered");
+
+        buffer.push(b"1}\r\n\r\n: keep-alive\n\ndata: {\"b\":2}\n\n");
+        assert_eq!(buffer.next_data(), Some(r#"{"a":1}"#.to_string()));
+        assert_eq!(buffer.next_data(), Some(r#"{"b":2}"#.to_string()));
+        assert_eq!(buffer.next_data(), None);
+    }
+
+    #[test]
+    fn sse_buffer_joins_characters_split_across_chunks() {
+        let mut buffer = SseBuffer::default();
+        let event = "data: caf\u{e9} \u{1f642}\n\n".as_bytes();
+
+        buffer.push(&event[..10]); // Inside é
+        buffer.push(&event[10..14]); // Inside 🙂
+        assert_eq!(buffer.next_data(), None);
+        buffer.push(&event[14..]);
+        assert_eq!(buffer.next_data(), Some("caf\u{e9} \u{1f642}".to_string()));
+    }
+
+    #[test]
+    fn stream_events_emit_deltas_then_response() -> Result<()> {
+        let events = [
+            r#"{"type": "response.created", "response": {}}"#,
+            r#"{"type": "response.output_text.delta", "delta": "Hello"}"#,
+            r#"{"type": "response.output_text.delta", "delta": " world"}"#,
+            r#"{
+                "type": "response.completed",
+                "response": {
+                    "created_at": 1688413145,
+                    "model": "gpt-5",
+                    "output": [{
+                        "type": "message",
+                        "role": "assistant",
+                        "content": [{"type": "output_text", "text": "Hello world"}]
+                    }],
+                    "usage": {"input_tokens": 1, "output_tokens": 2, "total_tokens": 3}
+                }
+            }"#,
+        ];
+
+        let mut deltas = vec![];
+        let mut on_event = |e: &StreamEvent| {
+            if let StreamEvent::OutputTextDelta(d) = e {
+                deltas.push(d.clone());
+            }
+            Ok(())
+        };
+
+        let mut completed = None;
+        for data in events {
+            let event = serde_json::from_str::<ResponsesStreamEvent>(data)?;
+            if let Some(resp) = handle_stream_event(event, &mut on_event)? {
+                completed = Some(resp);
+            }
+        }
+
+        assert_eq!(deltas, vec!["Hello", " world"]);
+        let completed = completed.expect("should complete");
+        assert_eq!(completed.choices[0].message.content, "Hello world");
+        assert_eq!(completed.usage.total_tokens, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn stream_failed_event_is_error() -> Result<()> {
+        let data = r#"{
+            "type": "response.failed",
+            "response": {
+                "error": {"code": "server_error", "message": "The model failed"}
+            }
+        }"#;
+
+        let event = serde_json::from_str::<ResponsesStreamEvent>(data)?;
+        let err = handle_stream_event(event, &mut |_| Ok(())).expect_err("should fail");
+
+        assert!(
+            matches!(&err, Error::OpenAIError { error } if error.message == "The model failed"),
+            "unexpected error: {err:?}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn parse_response_error() -> Result<()> {
         let data = r#"{
@@ -1,19 +1,340 @@
-//! Parse from input streams
+//! Parse from input streams, and write messages back into them
 
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::openai::Message;
 use crate::Error;
+use crate::attachment::{Attachment, AttachmentKind, LocalFiles};
+use crate::openai::{Message, Role, ToolCall};
 
-/// Read from `std::io::Read` into a vector of messages
-pub fn parse_messages(input: &mut impl Read) -> Result<Vec<Message>, Error> {
+/// Prefix for role marker lines in transcripts, e.g. `>>> user`
+const ROLE_MARKER: &str = ">>>";
+
+/// Marker lines in transcripts
+#[derive(Debug, PartialEq)]
+enum Marker {
+    /// `>>> system`, `>>> user`, or `>>> assistant`
+    Role(Role),
+    /// `>>> tool_call <call_id> <name>`, with arguments as body
+    ToolCall { call_id: String, name: String },
+    /// `>>> tool <call_id>`, with tool output as body
+    ToolOutput { call_id: String },
+    /// `>>> image <path or url>` or `>>> file <path>`, without body
+    Attachment(AttachmentKind, String),
+}
+
+/// Formats that input streams can be in
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum InputFormat {
+    /// Transcript if input begins with a role marker line, otherwise text
+    Auto,
+    /// Entire input is a single user message
+    #[default]
+    Text,
+    /// Messages delimited by role marker lines, e.g. `>>> system`, `>>> user`, `>>> assistant`,
+    /// `>>> tool_call <call_id> <name>`, `>>> tool <call_id>`. Attachments are marker lines like
+    /// `>>> image <path or url>` and `>>> file <path>`
+    Transcript,
+    /// JSON array of `{"role": ..., "content": ...}` objects
+    JSON,
+    /// One `{"role": ..., "content": ...}` object per line
+    NDJSON,
+}
+
+/// Read from `std::io::Read` into a vector of messages. Attachments referencing local files are
+/// refused, see `parse_messages_with`
+pub fn parse_messages(input: &mut impl Read, format: InputFormat) -> Result<Vec<Message>, Error> {
+    parse_messages_with(input, format, &LocalFiles::Deny)
+}
+
+/// Read from `std::io::Read` into a vector of messages, loading attachments of local files per
+/// `local`
+pub fn parse_messages_with(
+    input: &mut impl Read,
+    format: InputFormat,
+    local: &LocalFiles,
+) -> Result<Vec<Message>, Error> {
     let mut content = String::new();
     input.read_to_string(&mut content).map_err(Error::IO)?;
 
+    let messages = match format {
+        InputFormat::Auto if is_transcript(&content) => parse_transcript(&content),
+        InputFormat::Auto | InputFormat::Text => parse_text(&content),
+        InputFormat::Transcript => parse_transcript(&content),
+        InputFormat::JSON => parse_json(&content),
+        InputFormat::NDJSON => parse_ndjson(&content),
+    }?;
+
+    load_attachments(messages, local)
+}
+
+/// Load attachments referenced by `messages` from their sources, reading local files per `local`
+pub(crate) fn load_attachments(
+    messages: Vec<Message>,
+    local: &LocalFiles,
+) -> Result<Vec<Message>, Error> {
+    messages
+        .into_iter()
+        .map(|msg| {
+            let attachments = msg
+                .attachments
+                .into_iter()
+                .map(|attachment| attachment.load_with(local))
+                .collect::<Result<_, _>>()?;
+            Ok(Message { attachments, ..msg })
+        })
+        .collect()
+}
+
+fn parse_text(content: &str) -> Result<Vec<Message>, Error> {
     if content.trim().is_empty() {
         Ok(vec![])
     } else {
-        Ok(vec![Message::user(&content)])
+        Ok(vec![Message::user(content)])
+    }
+}
+
+/// Parse JSON array of messages
+fn parse_json(content: &str) -> Result<Vec<Message>, Error> {
+    if content.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    serde_json::from_str::<Vec<serde_json::Value>>(content)
+        .map_err(Error::JSON)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, record)| {
+            serde_json::from_value(record)
+                .map_err(|e| e.to_string())
+                .and_then(check_tool_call_id)
+                .map_err(|reason| Error::InvalidMessageRecord { index, reason })
+        })
+        .collect()
+}
+
+/// Parse newline-delimited JSON messages, skipping blank lines
+fn parse_ndjson(content: &str) -> Result<Vec<Message>, Error> {
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| e.to_string())
+                .and_then(check_tool_call_id)
+                .map_err(|reason| Error::InvalidMessageRecord { index, reason })
+        })
+        .collect()
+}
+
+/// `msg` if it isn't tool output, or is tool output naming the call it answers
+pub(crate) fn check_tool_call_id(msg: Message) -> Result<Message, String> {
+    match (&msg.role, msg.tool_call_id.as_deref()) {
+        (Role::Tool, None | Some("")) => Err("tool message missing \"tool_call_id\"".to_string()),
+        _ => Ok(msg),
+    }
+}
+
+/// Write messages as a transcript that `parse_messages` can read back
+pub fn write_transcript(dest: &mut impl Write, messages: &[Message]) -> Result<(), Error> {
+    for (idx, msg) in messages.iter().enumerate() {
+        if idx != 0 {
+            writeln!(dest)?;
+        }
+        match (&msg.role, &msg.tool_call_id) {
+            (Role::Tool, Some(call_id)) => writeln!(dest, "{ROLE_MARKER} tool {call_id}")?,
+            (role, _) => writeln!(dest, "{ROLE_MARKER} {}", role.as_str())?,
+        }
+        write_transcript_body(dest, &msg.content)?;
+
+        for attachment in &msg.attachments {
+            writeln!(dest)?;
+            let kind = match attachment.kind {
+                AttachmentKind::Image => "image",
+                AttachmentKind::File => "file",
+            };
+            writeln!(dest, "{ROLE_MARKER} {kind} {}", attachment.source)?;
+        }
+
+        for call in &msg.tool_calls {
+            writeln!(dest)?;
+            writeln!(
+                dest,
+                "{ROLE_MARKER} tool_call {} {}",
+                call.call_id, call.name
+            )?;
+            write_transcript_body(dest, &call.arguments)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_transcript_body(dest: &mut impl Write, body: &str) -> Result<(), Error> {
+    for line in body.lines() {
+        if is_escapable(line) {
+            writeln!(dest, "\\{line}")?;
+        } else {
+            writeln!(dest, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether content's first non-blank line is a valid role marker
+fn is_transcript(content: &str) -> bool {
+    content
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .and_then(|l| l.strip_prefix(ROLE_MARKER))
+        .is_some_and(|marker| parse_marker(marker).is_ok())
+}
+
+/// Whether body line would be mistaken for a role marker or escaped line, i.e. matches `^\\*>>>`
+fn is_escapable(line: &str) -> bool {
+    line.trim_start_matches('\\').starts_with(ROLE_MARKER)
+}
+
+/// Parse transcript, where each message begins with a role marker line.
+/// Body lines matching `^\\+>>>` have a single leading `\` removed, so `\>>>` reads as `>>>`.
+/// Tool calls attach to the assistant message directly preceding them, and attachments to the
+/// message directly preceding them.
+fn parse_transcript(content: &str) -> Result<Vec<Message>, Error> {
+    let mut messages = vec![];
+    let mut current: Option<(Marker, usize, Vec<&str>)> = None;
+
+    for (idx, line) in content.lines().enumerate() {
+        let line_num = idx + 1;
+
+        if let Some(marker) = line.strip_prefix(ROLE_MARKER) {
+            let marker = parse_marker(marker).map_err(|reason| Error::InvalidTranscript {
+                line: line_num,
+                reason,
+            })?;
+            if let Some((marker, line, body)) = current.take() {
+                push_transcript_entry(&mut messages, marker, &body)
+                    .map_err(|reason| Error::InvalidTranscript { line, reason })?;
+            }
+            current = Some((marker, line_num, vec![]));
+            continue;
+        }
+
+        match current.as_mut() {
+            Some((_, _, body)) if is_escapable(line) => body.push(&line[1..]),
+            Some((_, _, body)) => body.push(line),
+            None if line.trim().is_empty() => (),
+            None => {
+                return Err(Error::InvalidTranscript {
+                    line: line_num,
+                    reason: format!(
+                        "expected role marker (e.g. \"{ROLE_MARKER} user\") before content"
+                    ),
+                });
+            }
+        }
+    }
+
+    if let Some((marker, line, body)) = current.take() {
+        push_transcript_entry(&mut messages, marker, &body)
+            .map_err(|reason| Error::InvalidTranscript { line, reason })?;
+    }
+
+    Ok(messages)
+}
+
+/// Parse text following `ROLE_MARKER` on a marker line
+fn parse_marker(marker: &str) -> Result<Marker, String> {
+    // Attachment sources may contain spaces
+    match marker.trim().split_once(char::is_whitespace) {
+        Some(("image", source)) => {
+            return Ok(Marker::Attachment(
+                AttachmentKind::Image,
+                source.trim().to_string(),
+            ));
+        }
+        Some(("file", source)) => {
+            return Ok(Marker::Attachment(
+                AttachmentKind::File,
+                source.trim().to_string(),
+            ));
+        }
+        _ => (),
+    }
+
+    let parts = marker.split_whitespace().collect::<Vec<_>>();
+    match parts.as_slice() {
+        ["tool_call", call_id, name] => Ok(Marker::ToolCall {
+            call_id: call_id.to_string(),
+            name: name.to_string(),
+        }),
+        ["tool_call", ..] => Err("expected \"tool_call <call_id> <name>\"".to_string()),
+        ["tool", call_id] => Ok(Marker::ToolOutput {
+            call_id: call_id.to_string(),
+        }),
+        ["tool", ..] => Err("expected \"tool <call_id>\"".to_string()),
+        ["image"] => Err("expected \"image <path or url>\"".to_string()),
+        ["file"] => Err("expected \"file <path>\"".to_string()),
+        [role] => parse_role(role).map(Marker::Role),
+        [] => Err("missing role after marker".to_string()),
+        _ => Err(format!("unknown role \"{}\"", marker.trim())),
+    }
+}
+
+fn parse_role(role: &str) -> Result<Role, String> {
+    match role {
+        "system" => Ok(Role::System),
+        "user" => Ok(Role::User),
+        "assistant" => Ok(Role::Assistant),
+        other => Err(format!(
+            "unknown role \"{other}\", expected one of system, user, assistant, tool, tool_call, image, file"
+        )),
+    }
+}
+
+/// Append entry for marker and body lines to `messages`
+fn push_transcript_entry(
+    messages: &mut Vec<Message>,
+    marker: Marker,
+    body: &[&str],
+) -> Result<(), String> {
+    let content = transcript_body(body);
+    match marker {
+        Marker::Role(role) => messages.push(Message::new(role, &content)),
+        Marker::ToolOutput { call_id } => messages.push(Message::tool(&call_id, &content)),
+        Marker::ToolCall { call_id, name } => {
+            let call = ToolCall {
+                call_id,
+                name,
+                arguments: content,
+            };
+            match messages.last_mut() {
+                Some(msg) if msg.role == Role::Assistant => msg.tool_calls.push(call),
+                _ => messages.push(Message::tool_calls(vec![call])),
+            }
+        }
+        Marker::Attachment(kind, source) => {
+            if !content.is_empty() {
+                return Err("unexpected content after attachment".to_string());
+            }
+            let attachment = Attachment::new(kind, &source);
+            match messages.last_mut() {
+                Some(msg) => msg.attachments.push(attachment),
+                None => messages.push(Message {
+                    attachments: vec![attachment],
+                    ..Message::user("")
+                }),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Join body lines, dropping surrounding blank lines
+fn transcript_body(body: &[&str]) -> String {
+    let start = body.iter().position(|l| !l.trim().is_empty());
+    let end = body.iter().rposition(|l| !l.trim().is_empty());
+    match (start, end) {
+        (Some(start), Some(end)) => body[start..=end].join("\n"),
+        _ => String::new(),
     }
 }
 
@@ -21,12 +342,14 @@ pub fn parse_messages(input: &mut impl Read) -> Result<Vec<Message>, Error> {
 mod test {
     use super::*;
     use assert_fs::prelude::*;
+    use serde_json::json;
     use std::fs::File;
 
     #[test]
     fn parse_short_message() {
         let mut data = "Hello world".as_bytes();
-        let messages = parse_messages(&mut data).expect("parse_messages should succeed");
+        let messages =
+            parse_messages(&mut data, InputFormat::Text).expect("parse_messages should succeed");
         assert_eq!(
             messages,
             vec![Message::user("Hello world")],
@@ -37,7 +360,8 @@ mod test {
     #[test]
     fn parse_empty_input() {
         let mut data = "".as_bytes();
-        let messages = parse_messages(&mut data).expect("parse_messages should succeed");
+        let messages =
+            parse_messages(&mut data, InputFormat::Text).expect("parse_messages should succeed");
         assert_eq!(messages, vec![], "Should have no messages");
     }
 
@@ -47,8 +371,363 @@ mod test {
         file.write_str("Hello world").unwrap();
 
         let mut file = File::open(file.path()).unwrap();
-        let messages = parse_messages(&mut file).expect("should succeed");
+        let messages = parse_messages(&mut file, InputFormat::Text).expect("should succeed");
 
         assert_eq!(messages, vec![Message::user("Hello world")]);
     }
+
+    #[test]
+    fn parse_transcript_messages() {
+        let mut data = r#">>> system
+You are a calculator
+
+>>> user
+1 + 1
+
+>>> assistant
+2
+>>> user
+Multiline
+    indented
+
+\>>> escaped marker
+\begin{not escaped}
+"#
+        .as_bytes();
+
+        let messages = parse_messages(&mut data, InputFormat::Transcript).expect("should succeed");
+
+        assert_eq!(
+            messages,
+            vec![
+                Message::system("You are a calculator"),
+                Message::user("1 + 1"),
+                Message::assistant("2"),
+                Message::user(
+                    "Multiline\n    indented\n\n>>> escaped marker\n\\begin{not escaped}"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_transcript_empty() {
+        let mut data = "\n\n".as_bytes();
+        let messages = parse_messages(&mut data, InputFormat::Transcript).expect("should succeed");
+        assert_eq!(messages, vec![]);
+    }
+
+    #[test]
+    fn parse_transcript_content_before_marker() {
+        let mut data = "\nHello\n>>> user\nWorld".as_bytes();
+        let err = parse_messages(&mut data, InputFormat::Transcript).expect_err("should fail");
+        assert!(
+            matches!(err, Error::InvalidTranscript { line: 2, .. }),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_transcript_unknown_role() {
+        let mut data = ">>> user\nHello\n>>> robot\nHi".as_bytes();
+        let err = parse_messages(&mut data, InputFormat::Transcript).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidTranscript { line: 3, reason } if reason.contains("robot")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_transcript_missing_role() {
+        let mut data = ">>>\nHello".as_bytes();
+        let err = parse_messages(&mut data, InputFormat::Transcript).expect_err("should fail");
+        assert!(
+            matches!(err, Error::InvalidTranscript { line: 1, .. }),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_transcript_tool_calls() {
+        let mut data = r#">>> user
+Weather in Tokyo?
+
+>>> assistant
+
+>>> tool_call call_1 get_weather
+{"city": "Tokyo"}
+
+>>> tool call_1
+Sunny
+
+>>> assistant
+It is sunny
+"#
+        .as_bytes();
+
+        let messages = parse_messages(&mut data, InputFormat::Transcript).expect("should succeed");
+
+        assert_eq!(
+            messages,
+            vec![
+                Message::user("Weather in Tokyo?"),
+                Message::tool_calls(vec![ToolCall {
+                    call_id: "call_1".to_string(),
+                    name: "get_weather".to_string(),
+                    arguments: r#"{"city": "Tokyo"}"#.to_string(),
+                }]),
+                Message::tool("call_1", "Sunny"),
+                Message::assistant("It is sunny"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_transcript_attachments() {
+        let mut data = r#">>> user
+What is in these?
+
+>>> image https://example.com/cat.jpg
+
+>>> image https://example.com/my dog.png
+"#
+        .as_bytes();
+
+        let messages = parse_messages(&mut data, InputFormat::Transcript).expect("should succeed");
+
+        let image = |url: &str| Attachment {
+            kind: AttachmentKind::Image,
+            source: url.to_string(),
+            url: url.to_string(),
+        };
+        assert_eq!(
+            messages,
+            vec![Message {
+                attachments: vec![
+                    image("https://example.com/cat.jpg"),
+                    image("https://example.com/my dog.png"),
+                ],
+                ..Message::user("What is in these?")
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_transcript_attachment_with_content() {
+        let mut data = ">>> user\nHi\n>>> image https://example.com/cat.jpg\nA cat".as_bytes();
+        let err = parse_messages(&mut data, InputFormat::Transcript).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidTranscript { line: 3, reason } if reason.contains("attachment")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_json_attachments_are_loaded() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        dir.child("notes.txt").write_str("hi").unwrap();
+
+        let data = json!([{
+            "role": "user",
+            "content": "Summarize",
+            "attachments": [{"type": "file", "source": "notes.txt"}],
+        }])
+        .to_string();
+
+        let local = LocalFiles::Allow(dir.path().to_path_buf());
+        let messages = parse_messages_with(&mut data.as_bytes(), InputFormat::JSON, &local)
+            .expect("should succeed");
+        assert_eq!(
+            messages[0].attachments[0].url,
+            "data:text/plain;base64,aGk="
+        );
+    }
+
+    #[test]
+    fn parse_local_attachments_denied() {
+        let mut data = ">>> user\nSummarize\n>>> file notes.txt".as_bytes();
+        let err = parse_messages(&mut data, InputFormat::Transcript).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidAttachment(reason) if reason.starts_with("notes.txt")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_transcript_tool_missing_call_id() {
+        let mut data = ">>> user\nHello\n>>> tool\nSunny".as_bytes();
+        let err = parse_messages(&mut data, InputFormat::Transcript).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidTranscript { line: 3, reason } if reason.contains("call_id")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_json_messages() {
+        let mut data = r#"[
+            {"role": "system", "content": "SYSTEM"},
+            {"role": "user", "content": "USER"},
+            {"role": "assistant", "content": "ASSISTANT"}
+        ]"#
+        .as_bytes();
+
+        let messages = parse_messages(&mut data, InputFormat::JSON).expect("should succeed");
+        assert_eq!(
+            messages,
+            vec![
+                Message::system("SYSTEM"),
+                Message::user("USER"),
+                Message::assistant("ASSISTANT"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_json_invalid_role() {
+        let mut data = r#"[
+            {"role": "user", "content": "USER"},
+            {"role": "robot", "content": "BEEP"}
+        ]"#
+        .as_bytes();
+
+        let err = parse_messages(&mut data, InputFormat::JSON).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidMessageRecord { index: 1, reason } if reason.contains("robot")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_json_tool_missing_call_id() {
+        let mut data = r#"[
+            {"role": "user", "content": "USER"},
+            {"role": "tool", "content": "Sunny"}
+        ]"#
+        .as_bytes();
+
+        let err = parse_messages(&mut data, InputFormat::JSON).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidMessageRecord { index: 1, reason } if reason.contains("tool_call_id")),
+            "unexpected error: {err:?}"
+        );
+
+        let mut data = r#"{"role": "tool", "content": "Sunny", "tool_call_id": ""}"#.as_bytes();
+        let err = parse_messages(&mut data, InputFormat::NDJSON).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidMessageRecord { index: 0, reason } if reason.contains("tool_call_id")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_json_not_array() {
+        let mut data = r#"{"role": "user", "content": "USER"}"#.as_bytes();
+        let err = parse_messages(&mut data, InputFormat::JSON).expect_err("should fail");
+        assert!(matches!(err, Error::JSON(_)), "unexpected error: {err:?}");
+    }
+
+    #[test]
+    fn parse_ndjson_messages() {
+        let mut data = "{\"role\": \"system\", \"content\": \"SYSTEM\"}\n\n{\"role\": \"user\", \"content\": \"USER\"}\n".as_bytes();
+
+        let messages = parse_messages(&mut data, InputFormat::NDJSON).expect("should succeed");
+        assert_eq!(
+            messages,
+            vec![Message::system("SYSTEM"), Message::user("USER")]
+        );
+    }
+
+    #[test]
+    fn parse_ndjson_missing_field() {
+        let mut data =
+            "{\"role\": \"user\", \"content\": \"USER\"}\n{\"role\": \"user\"}\n".as_bytes();
+
+        let err = parse_messages(&mut data, InputFormat::NDJSON).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidMessageRecord { index: 1, reason } if reason.contains("content")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_auto_detects_transcript() {
+        let mut data = "\n>>> user\nHello".as_bytes();
+        let messages = parse_messages(&mut data, InputFormat::Auto).expect("should succeed");
+        assert_eq!(messages, vec![Message::user("Hello")]);
+    }
+
+    #[test]
+    fn parse_auto_detects_text() {
+        let mut data = ">>> not a role\nHello".as_bytes();
+        let messages = parse_messages(&mut data, InputFormat::Auto).expect("should succeed");
+        assert_eq!(messages, vec![Message::user(">>> not a role\nHello")]);
+    }
+
+    #[test]
+    fn write_transcript_round_trips() {
+        let messages = vec![
+            Message::system("SYSTEM"),
+            Message::user("Line 1\n>>> assistant\n\\>>> user\n\\n"),
+            Message::assistant("REPLY"),
+        ];
+
+        let mut output = vec![];
+        write_transcript(&mut output, &messages).expect("should succeed");
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            ">>> system\nSYSTEM\n\n>>> user\nLine 1\n\\>>> assistant\n\\\\>>> user\n\\n\n\n>>> assistant\nREPLY\n"
+        );
+
+        let parsed = parse_messages(&mut output.as_slice(), InputFormat::Transcript).unwrap();
+        assert_eq!(parsed, messages);
+    }
+
+    #[test]
+    fn write_transcript_tool_calls_round_trips() {
+        let call = |id: &str| ToolCall {
+            call_id: id.to_string(),
+            name: "get_time".to_string(),
+            arguments: "{}".to_string(),
+        };
+        let messages = vec![
+            Message::user("Time?"),
+            Message::tool_calls(vec![call("call_1"), call("call_2")]),
+            Message::tool("call_1", "12:00"),
+            Message::tool("call_2", "13:00"),
+        ];
+
+        let mut output = vec![];
+        write_transcript(&mut output, &messages).expect("should succeed");
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            ">>> user\nTime?\n\n>>> assistant\n\n>>> tool_call call_1 get_time\n{}\n\n>>> tool_call call_2 get_time\n{}\n\n>>> tool call_1\n12:00\n\n>>> tool call_2\n13:00\n"
+        );
+
+        let parsed = parse_messages(&mut output.as_slice(), InputFormat::Transcript).unwrap();
+        assert_eq!(parsed, messages);
+    }
+
+    #[test]
+    fn write_transcript_attachments_round_trips() {
+        let url = "https://example.com/cat.jpg";
+        let messages = vec![Message {
+            attachments: vec![Attachment {
+                kind: AttachmentKind::Image,
+                source: url.to_string(),
+                url: url.to_string(),
+            }],
+            ..Message::user("Cat?")
+        }];
+
+        let mut output = vec![];
+        write_transcript(&mut output, &messages).expect("should succeed");
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            format!(">>> user\nCat?\n\n>>> image {url}\n")
+        );
+
+        let parsed = parse_messages(&mut output.as_slice(), InputFormat::Transcript).unwrap();
+        assert_eq!(parsed, messages);
+    }
 }
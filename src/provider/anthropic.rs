@@ -0,0 +1,732 @@
+//! Provider for Anthropic Messages API
+//! Reference: <https://docs.anthropic.com/en/api/messages>
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::Error;
+use crate::attachment::{Attachment, AttachmentKind};
+use crate::openai::{
+    APIError, Choice, FinishReason, Message, Response, ResponseRequest, Role, SseBuffer,
+    StreamEvent, ToolCall, Usage,
+};
+use crate::provider::{OnEvent, Provider};
+use crate::retry::{self, RetryPolicy};
+
+/// Version of API requests are made against
+const API_VERSION: &str = "2023-06-01";
+
+/// Value for required `max_tokens` field, unless `max_output_tokens` is given
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Client for `/v1/messages` endpoint
+pub struct Client {
+    /// Inner client
+    client: reqwest::Client,
+    /// API Key
+    api_key: Option<String>,
+    /// Base URL for API Endpoint
+    base_url: String,
+    /// Policy for retrying transient failures
+    retry_policy: RetryPolicy,
+}
+
+impl Client {
+    pub fn new(api_key: Option<String>, base_url: String) -> Result<Self, Error> {
+        let client = reqwest::Client::builder().build().map_err(Error::from)?;
+        Ok(Self {
+            client,
+            api_key,
+            base_url,
+            retry_policy: RetryPolicy::none(),
+        })
+    }
+
+    /// Set policy for retrying rate limited, overloaded, server error, and connection failures
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Send payload with retries, mapping non-200 statuses to API errors
+    async fn send(&self, payload: &Value, timeout: Duration) -> Result<reqwest::Response, Error> {
+        let api_key = &self.api_key.as_ref().ok_or(Error::NoAPIKey)?;
+
+        let (resp, attempts) = retry::send(&self.retry_policy, || {
+            self.client
+                .post(self.messages_endpoint())
+                .header("x-api-key", api_key.as_str())
+                .header("anthropic-version", API_VERSION)
+                .timeout(timeout)
+                .header("Content-Type", "application/json")
+                .json(payload)
+        })
+        .await?;
+
+        match resp.status() {
+            StatusCode::OK => Ok(resp),
+            status => {
+                let body = resp.text().await.map_err(Error::from)?;
+                let error = match serde_json::from_str::<ErrorContainer>(&body) {
+                    Ok(container) => Error::api(status.as_u16(), container.into()),
+                    Err(_) => Error::UnexpectedResponse(format!("{status} - {body}")),
+                };
+                Err(retry::exhausted(error, attempts))
+            }
+        }
+    }
+
+    fn messages_endpoint(&self) -> String {
+        format!("{}{}", self.base_url, "/v1/messages")
+    }
+}
+
+#[async_trait]
+impl Provider for Client {
+    async fn create_response(&self, request: &ResponseRequest) -> Result<Response, Error> {
+        let resp = self.send(&to_payload(request), request.timeout).await?;
+        let message: MessagesResponse = resp.json().await.map_err(Error::from)?;
+        Ok(message.into())
+    }
+
+    async fn stream_response(
+        &self,
+        request: &ResponseRequest,
+        on_event: &mut OnEvent<'_>,
+    ) -> Result<Response, Error> {
+        let mut payload = to_payload(request);
+        if let Some(obj) = payload.as_object_mut() {
+            obj.insert("stream".to_string(), json!(true));
+        }
+
+        let mut resp = self.send(&payload, request.timeout).await?;
+        let mut buffer = SseBuffer::default();
+        let mut state = StreamState::default();
+
+        while let Some(chunk) = resp.chunk().await.map_err(Error::from)? {
+            buffer.push(&chunk);
+            while let Some(data) = buffer.next_data() {
+                let event = serde_json::from_str::<MessagesStreamEvent>(&data)?;
+                if let Some(response) = state.handle(event, on_event)? {
+                    return Ok(response);
+                }
+            }
+        }
+
+        Err(Error::UnexpectedResponse(
+            "stream ended before response completed".to_string(),
+        ))
+    }
+}
+
+/// Messages API payload for request. System messages are hoisted into top-level `system`
+fn to_payload(request: &ResponseRequest) -> Value {
+    let system = request
+        .messages
+        .iter()
+        .filter(|m| m.role == Role::System)
+        .map(|m| m.content.as_str())
+        .collect::<Vec<_>>();
+
+    let messages = request
+        .messages
+        .iter()
+        .filter(|m| m.role != Role::System)
+        .map(to_message)
+        .collect::<Vec<_>>();
+
+    let mut payload = json!({
+        "model": request.model,
+        "max_tokens": request.max_output_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "messages": messages,
+    });
+    let Some(obj) = payload.as_object_mut() else {
+        return payload;
+    };
+
+    if !system.is_empty() {
+        obj.insert("system".to_string(), json!(system.join("\n\n")));
+    }
+
+    if let Some(temperature) = request.temperature {
+        obj.insert("temperature".to_string(), json!(temperature));
+    }
+
+    if request.reasoning.is_some() {
+        log::warn!("reasoning effort is not supported by anthropic provider, ignoring");
+    }
+
+    if request.text_format.is_some() {
+        log::warn!(
+            "structured output is not supported by anthropic provider, output is only validated"
+        );
+    }
+
+    if !request.tools.is_empty() {
+        let tools = request
+            .tools
+            .iter()
+            .map(|tool| {
+                let mut value = json!({
+                    "name": tool.name,
+                    "input_schema": tool.parameters,
+                });
+                if let (Some(obj), Some(description)) = (value.as_object_mut(), &tool.description) {
+                    obj.insert("description".to_string(), json!(description));
+                }
+                value
+            })
+            .collect();
+        obj.insert("tools".to_string(), Value::Array(tools));
+    }
+
+    payload
+}
+
+/// Messages API shape for message. Tool outputs are `tool_result` blocks in user messages
+fn to_message(msg: &Message) -> Value {
+    if msg.role == Role::Tool {
+        return json!({
+            "role": "user",
+            "content": [{
+                "type": "tool_result",
+                "tool_use_id": msg.tool_call_id.clone().unwrap_or_default(),
+                "content": msg.content,
+            }],
+        });
+    }
+
+    if msg.tool_calls.is_empty() && msg.attachments.is_empty() {
+        return json!({
+            "role": msg.role.as_str(),
+            "content": msg.content,
+        });
+    }
+
+    let mut content = vec![];
+    if !msg.content.is_empty() {
+        content.push(json!({"type": "text", "text": msg.content}));
+    }
+    content.extend(msg.attachments.iter().map(to_attachment_block));
+    content.extend(msg.tool_calls.iter().map(|call| {
+        let input = serde_json::from_str::<Value>(&call.arguments).unwrap_or(json!({}));
+        json!({
+            "type": "tool_use",
+            "id": call.call_id,
+            "name": call.name,
+            "input": input,
+        })
+    }));
+
+    json!({
+        "role": msg.role.as_str(),
+        "content": content,
+    })
+}
+
+/// Messages API content block for attachment. Files are sent as `document` blocks
+fn to_attachment_block(attachment: &Attachment) -> Value {
+    let block_type = match attachment.kind {
+        AttachmentKind::Image => "image",
+        AttachmentKind::File => "document",
+    };
+    let source = match attachment.data() {
+        Some((media_type, data)) => json!({
+            "type": "base64",
+            "media_type": media_type,
+            "data": data,
+        }),
+        None => json!({"type": "url", "url": attachment.url}),
+    };
+    json!({"type": block_type, "source": source})
+}
+
+/// Map Messages API `stop_reason` into `FinishReason`
+fn finish_reason(stop_reason: Option<&str>) -> FinishReason {
+    match stop_reason {
+        Some("max_tokens") => FinishReason::Length,
+        Some("tool_use") => FinishReason::FunctionCall,
+        Some("refusal") => FinishReason::ContentFilter,
+        _ => FinishReason::Stop,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorContainer {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    #[serde(rename = "type")]
+    error_type: String,
+    message: String,
+}
+
+impl From<ErrorContainer> for APIError {
+    fn from(container: ErrorContainer) -> Self {
+        APIError {
+            message: container.error.message,
+            error_type: container.error.error_type,
+            param: None,
+            code: None,
+            status: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    model: String,
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: MessagesUsage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MessagesUsage {
+    /// Input tokens after the last cache breakpoint, excluding cache reads and writes
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+impl From<&MessagesUsage> for Usage {
+    fn from(usage: &MessagesUsage) -> Self {
+        let input_tokens =
+            usage.input_tokens + usage.cache_read_input_tokens + usage.cache_creation_input_tokens;
+        Usage {
+            input_tokens,
+            output_tokens: usage.output_tokens,
+            total_tokens: input_tokens + usage.output_tokens,
+            cached_tokens: usage.cache_read_input_tokens,
+            reasoning_tokens: 0,
+        }
+    }
+}
+
+impl From<MessagesResponse> for Response {
+    fn from(value: MessagesResponse) -> Self {
+        let mut text = String::new();
+        let mut tool_calls = vec![];
+
+        for block in value.content {
+            match block {
+                ContentBlock::Text { text: t } => text.push_str(&t),
+                ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    call_id: id,
+                    name,
+                    arguments: input.to_string(),
+                }),
+                ContentBlock::Other => (),
+            }
+        }
+
+        Response {
+            id: None,
+            created: Utc::now(),
+            choices: vec![Choice {
+                message: Message {
+                    tool_calls,
+                    ..Message::assistant(&text)
+                },
+                finish_reason: finish_reason(value.stop_reason.as_deref()),
+            }],
+            model: value.model,
+            usage: (&value.usage).into(),
+            cached: false,
+        }
+    }
+}
+
+/// Server-sent events from the Messages API with `"stream": true`
+/// Reference: <https://docs.anthropic.com/en/docs/build-with-claude/streaming>
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MessagesStreamEvent {
+    MessageStart {
+        message: StreamMessage,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: BlockDelta,
+    },
+    MessageDelta {
+        delta: MessageDelta,
+        #[serde(default)]
+        usage: Option<MessagesUsage>,
+    },
+    MessageStop,
+    Error {
+        error: ErrorBody,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamMessage {
+    model: String,
+    #[serde(default)]
+    usage: MessagesUsage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BlockDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageDelta {
+    stop_reason: Option<String>,
+}
+
+/// Response accumulated from stream events
+#[derive(Debug, Default)]
+struct StreamState {
+    model: String,
+    text: String,
+    /// Tool calls by content block index, with arguments accumulated from partial JSON
+    tool_calls: Vec<(usize, ToolCall)>,
+    stop_reason: Option<String>,
+    usage: MessagesUsage,
+}
+
+impl StreamState {
+    /// Handle a single stream event. Returns final response on completion
+    fn handle(
+        &mut self,
+        event: MessagesStreamEvent,
+        on_event: &mut OnEvent<'_>,
+    ) -> Result<Option<Response>, Error> {
+        match event {
+            MessagesStreamEvent::MessageStart { message } => {
+                self.model = message.model;
+                self.usage = message.usage;
+            }
+            MessagesStreamEvent::ContentBlockStart {
+                index,
+                content_block: ContentBlock::ToolUse { id, name, .. },
+            } => self.tool_calls.push((
+                index,
+                ToolCall {
+                    call_id: id,
+                    name,
+                    arguments: String::new(),
+                },
+            )),
+            MessagesStreamEvent::ContentBlockDelta {
+                delta: BlockDelta::TextDelta { text },
+                ..
+            } => {
+                self.text.push_str(&text);
+                on_event(&StreamEvent::OutputTextDelta(text))?;
+            }
+            MessagesStreamEvent::ContentBlockDelta {
+                index,
+                delta: BlockDelta::InputJsonDelta { partial_json },
+            } => {
+                if let Some((_, call)) = self.tool_calls.iter_mut().find(|(i, _)| *i == index) {
+                    call.arguments.push_str(&partial_json);
+                }
+            }
+            MessagesStreamEvent::MessageDelta { delta, usage } => {
+                self.stop_reason = delta.stop_reason;
+                if let Some(usage) = usage {
+                    self.usage.output_tokens = usage.output_tokens;
+                }
+            }
+            MessagesStreamEvent::MessageStop => {
+                let response = self.response();
+                on_event(&StreamEvent::Completed(response.clone()))?;
+                return Ok(Some(response));
+            }
+            MessagesStreamEvent::Error { error } => {
+                return Err(Error::OpenAIError {
+                    error: ErrorContainer { error }.into(),
+                });
+            }
+            MessagesStreamEvent::ContentBlockStart { .. }
+            | MessagesStreamEvent::ContentBlockDelta { .. }
+            | MessagesStreamEvent::Other => (),
+        }
+        Ok(None)
+    }
+
+    fn response(&self) -> Response {
+        let tool_calls = self
+            .tool_calls
+            .iter()
+            .map(|(_, call)| ToolCall {
+                arguments: match call.arguments.is_empty() {
+                    true => "{}".to_string(),
+                    false => call.arguments.clone(),
+                },
+                ..call.clone()
+            })
+            .collect();
+
+        Response {
+            id: None,
+            created: Utc::now(),
+            choices: vec![Choice {
+                message: Message {
+                    tool_calls,
+                    ..Message::assistant(&self.text)
+                },
+                finish_reason: finish_reason(self.stop_reason.as_deref()),
+            }],
+            model: self.model.clone(),
+            usage: (&self.usage).into(),
+            cached: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::openai::Tool;
+
+    use anyhow::Result;
+
+    #[test]
+    fn payload_hoists_system_messages() {
+        let request = ResponseRequest::builder()
+            .model("claude-sonnet-4-5".to_string())
+            .messages(vec![
+                Message::system("SYSTEM"),
+                Message::user("Time?"),
+                Message::tool_calls(vec![ToolCall {
+                    call_id: "toolu_1".to_string(),
+                    name: "get_time".to_string(),
+                    arguments: r#"{"tz":"UTC"}"#.to_string(),
+                }]),
+                Message::tool("toolu_1", "12:00"),
+            ])
+            .temperature(Some(0.5))
+            .timeout(Duration::from_secs(30))
+            .tools(vec![Tool {
+                name: "get_time".to_string(),
+                description: Some("Gets time".to_string()),
+                parameters: json!({"type": "object"}),
+                strict: None,
+            }])
+            .build()
+            .expect("request builds");
+
+        assert_eq!(
+            to_payload(&request),
+            json!({
+                "model": "claude-sonnet-4-5",
+                "max_tokens": 4096,
+                "system": "SYSTEM",
+                "temperature": 0.5,
+                "messages": [
+                    {"role": "user", "content": "Time?"},
+                    {"role": "assistant", "content": [
+                        {"type": "tool_use", "id": "toolu_1", "name": "get_time", "input": {"tz": "UTC"}},
+                    ]},
+                    {"role": "user", "content": [
+                        {"type": "tool_result", "tool_use_id": "toolu_1", "content": "12:00"},
+                    ]},
+                ],
+                "tools": [{
+                    "name": "get_time",
+                    "description": "Gets time",
+                    "input_schema": {"type": "object"},
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn message_with_attachments() {
+        let msg = Message {
+            attachments: vec![
+                Attachment {
+                    kind: AttachmentKind::Image,
+                    source: "cat.png".to_string(),
+                    url: "data:image/png;base64,iVBORw==".to_string(),
+                },
+                Attachment {
+                    kind: AttachmentKind::Image,
+                    source: "https://example.com/dog.jpg".to_string(),
+                    url: "https://example.com/dog.jpg".to_string(),
+                },
+                Attachment {
+                    kind: AttachmentKind::File,
+                    source: "report.pdf".to_string(),
+                    url: "data:application/pdf;base64,aGk=".to_string(),
+                },
+            ],
+            ..Message::user("Compare")
+        };
+
+        assert_eq!(
+            to_message(&msg),
+            json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "Compare"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw=="}},
+                    {"type": "image", "source": {"type": "url", "url": "https://example.com/dog.jpg"}},
+                    {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "aGk="}},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn response_from_message() -> Result<()> {
+        let data = r#"{
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "content": [
+                {"type": "text", "text": "Checking"},
+                {"type": "tool_use", "id": "toolu_1", "name": "get_time", "input": {}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "cache_read_input_tokens": 20, "output_tokens": 5}
+        }"#;
+
+        let resp = Response::from(serde_json::from_str::<MessagesResponse>(data)?);
+
+        assert_eq!(
+            resp.usage,
+            Usage {
+                input_tokens: 30,
+                output_tokens: 5,
+                total_tokens: 35,
+                cached_tokens: 20,
+                reasoning_tokens: 0,
+            }
+        );
+        assert_eq!(
+            resp.choices,
+            vec![Choice {
+                message: Message {
+                    tool_calls: vec![ToolCall {
+                        call_id: "toolu_1".to_string(),
+                        name: "get_time".to_string(),
+                        arguments: "{}".to_string(),
+                    }],
+                    ..Message::assistant("Checking")
+                },
+                finish_reason: FinishReason::FunctionCall,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn finish_reasons() {
+        assert_eq!(finish_reason(Some("end_turn")), FinishReason::Stop);
+        assert_eq!(finish_reason(Some("stop_sequence")), FinishReason::Stop);
+        assert_eq!(finish_reason(Some("max_tokens")), FinishReason::Length);
+        assert_eq!(finish_reason(Some("tool_use")), FinishReason::FunctionCall);
+        assert_eq!(finish_reason(Some("refusal")), FinishReason::ContentFilter);
+    }
+
+    #[test]
+    fn stream_events_build_response() -> Result<()> {
+        let events = [
+            r#"{"type": "message_start", "message": {"model": "claude-sonnet-4-5", "usage": {"input_tokens": 10, "output_tokens": 1}}}"#,
+            r#"{"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}"#,
+            r#"{"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}"#,
+            r#"{"type": "ping"}"#,
+            r#"{"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "get_time", "input": {}}}"#,
+            r#"{"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"tz\":"}}"#,
+            r#"{"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"UTC\"}"}}"#,
+            r#"{"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 7}}"#,
+            r#"{"type": "message_stop"}"#,
+        ];
+
+        let mut state = StreamState::default();
+        let mut deltas = vec![];
+        let mut response = None;
+        for event in events {
+            let event = serde_json::from_str::<MessagesStreamEvent>(event)?;
+            response = state.handle(event, &mut |e| {
+                if let StreamEvent::OutputTextDelta(delta) = e {
+                    deltas.push(delta.clone());
+                }
+                Ok(())
+            })?;
+        }
+
+        let response = response.expect("should complete");
+        assert_eq!(deltas, vec!["Hi".to_string()]);
+        assert_eq!(response.model, "claude-sonnet-4-5");
+        assert_eq!(response.usage.total_tokens, 17);
+        assert_eq!(
+            response.choices[0].message.tool_calls,
+            vec![ToolCall {
+                call_id: "toolu_1".to_string(),
+                name: "get_time".to_string(),
+                arguments: r#"{"tz":"UTC"}"#.to_string(),
+            }]
+        );
+        assert_eq!(
+            response.choices[0].finish_reason,
+            FinishReason::FunctionCall
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stream_error_event() {
+        let event = serde_json::from_str::<MessagesStreamEvent>(
+            r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#,
+        )
+        .unwrap();
+        let err = StreamState::default()
+            .handle(event, &mut |_| Ok(()))
+            .expect_err("should fail");
+        assert!(
+            matches!(&err, Error::OpenAIError { error } if error.error_type == "overloaded_error"),
+            "unexpected error: {err:?}"
+        );
+    }
+}
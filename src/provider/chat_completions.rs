@@ -0,0 +1,668 @@
+//! Provider for OpenAI-compatible Chat Completions APIs, as served by llama.cpp, vLLM, or Ollama
+//! Reference: <https://platform.openai.com/docs/api-reference/chat>
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::Error;
+use crate::attachment::AttachmentKind;
+use crate::openai::{
+    APIErrorContainer, Choice, FinishReason, InputTokensDetails, Message, OutputTokensDetails,
+    Response, ResponseRequest, Role, SseBuffer, StreamEvent, TextFormat, ToolCall, Usage,
+};
+use crate::provider::{OnEvent, Provider};
+use crate::retry::{self, RetryPolicy};
+
+/// Client for `/v1/chat/completions` endpoints
+pub struct Client {
+    /// Inner client
+    client: reqwest::Client,
+    /// API Key, which local servers may not need
+    api_key: Option<String>,
+    /// Base URL for API Endpoint
+    base_url: String,
+    /// Policy for retrying transient failures
+    retry_policy: RetryPolicy,
+}
+
+impl Client {
+    pub fn new(api_key: Option<String>, base_url: String) -> Result<Self, Error> {
+        let client = reqwest::Client::builder().build().map_err(Error::from)?;
+        Ok(Self {
+            client,
+            api_key,
+            base_url,
+            retry_policy: RetryPolicy::none(),
+        })
+    }
+
+    /// Set policy for retrying rate limited, server error, and connection failures
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Send payload with retries, mapping non-200 statuses to errors
+    async fn send(&self, payload: &Value, timeout: Duration) -> Result<reqwest::Response, Error> {
+        let (resp, attempts) = retry::send(&self.retry_policy, || {
+            let builder = self
+                .client
+                .post(self.completions_endpoint())
+                .timeout(timeout)
+                .header("Content-Type", "application/json")
+                .json(payload);
+            match &self.api_key {
+                Some(api_key) => builder.bearer_auth(api_key),
+                None => builder,
+            }
+        })
+        .await?;
+
+        match resp.status() {
+            StatusCode::OK => Ok(resp),
+            status => {
+                let body = resp.text().await.map_err(Error::from)?;
+                let error = match serde_json::from_str::<APIErrorContainer>(&body) {
+                    Ok(container) => Error::api(status.as_u16(), container.error),
+                    Err(_) => Error::UnexpectedResponse(format!("{status} - {body}")),
+                };
+                Err(retry::exhausted(error, attempts))
+            }
+        }
+    }
+
+    fn completions_endpoint(&self) -> String {
+        format!("{}{}", self.base_url, "/v1/chat/completions")
+    }
+}
+
+#[async_trait]
+impl Provider for Client {
+    async fn create_response(&self, request: &ResponseRequest) -> Result<Response, Error> {
+        let resp = self.send(&to_payload(request), request.timeout).await?;
+        let completion: Completion = resp.json().await.map_err(Error::from)?;
+        Response::try_from(completion).map_err(Error::UnexpectedResponse)
+    }
+
+    async fn stream_response(
+        &self,
+        request: &ResponseRequest,
+        on_event: &mut OnEvent<'_>,
+    ) -> Result<Response, Error> {
+        let mut payload = to_payload(request);
+        if let Some(obj) = payload.as_object_mut() {
+            obj.insert("stream".to_string(), json!(true));
+            obj.insert("stream_options".to_string(), json!({"include_usage": true}));
+        }
+
+        let mut resp = self.send(&payload, request.timeout).await?;
+        let mut buffer = SseBuffer::default();
+        let mut state = StreamState::default();
+
+        while let Some(chunk) = resp.chunk().await.map_err(Error::from)? {
+            buffer.push(&chunk);
+            while let Some(data) = buffer.next_data() {
+                if data.trim() == "[DONE]" {
+                    return state.finish(on_event);
+                }
+                if let Ok(container) = serde_json::from_str::<APIErrorContainer>(&data) {
+                    return Err(Error::OpenAIError {
+                        error: container.error,
+                    });
+                }
+                let chunk = serde_json::from_str::<CompletionChunk>(&data)?;
+                if let Some(delta) = state.push(chunk) {
+                    on_event(&StreamEvent::OutputTextDelta(delta))?;
+                }
+            }
+        }
+
+        // Some servers close the stream without `[DONE]` after the final chunk
+        if state.finish_reason.is_some() {
+            return state.finish(on_event);
+        }
+
+        Err(Error::UnexpectedResponse(
+            "stream ended before response completed".to_string(),
+        ))
+    }
+}
+
+/// Chat Completions payload for request
+fn to_payload(request: &ResponseRequest) -> Value {
+    let messages = request.messages.iter().map(to_message).collect::<Vec<_>>();
+
+    let mut payload = json!({
+        "model": request.model,
+        "messages": messages,
+    });
+    let Some(obj) = payload.as_object_mut() else {
+        return payload;
+    };
+
+    if let Some(temperature) = request.temperature {
+        obj.insert("temperature".to_string(), json!(temperature));
+    }
+
+    if let Some(max_output_tokens) = request.max_output_tokens {
+        obj.insert("max_tokens".to_string(), json!(max_output_tokens));
+    }
+
+    if let Some(reasoning) = &request.reasoning {
+        obj.insert("reasoning_effort".to_string(), json!(reasoning.effort));
+    }
+
+    if let Some(format) = &request.text_format {
+        let format = match format {
+            TextFormat::JsonObject => json!({"type": "json_object"}),
+            TextFormat::JsonSchema {
+                name,
+                schema,
+                strict,
+            } => json!({
+                "type": "json_schema",
+                "json_schema": {"name": name, "schema": schema, "strict": strict},
+            }),
+        };
+        obj.insert("response_format".to_string(), format);
+    }
+
+    if !request.tools.is_empty() {
+        let tools = request
+            .tools
+            .iter()
+            .map(|tool| json!({"type": "function", "function": tool}))
+            .collect();
+        obj.insert("tools".to_string(), Value::Array(tools));
+    }
+
+    payload
+}
+
+fn to_message(msg: &Message) -> Value {
+    let content = match msg.attachments.is_empty() {
+        true => json!(msg.content),
+        false => {
+            let text =
+                (!msg.content.is_empty()).then(|| json!({"type": "text", "text": msg.content}));
+            let attachments = msg.attachments.iter().map(|a| match a.kind {
+                AttachmentKind::Image => json!({
+                    "type": "image_url",
+                    "image_url": {"url": a.url},
+                }),
+                AttachmentKind::File => json!({
+                    "type": "file",
+                    "file": {"filename": a.filename(), "file_data": a.url},
+                }),
+            });
+            Value::Array(text.into_iter().chain(attachments).collect())
+        }
+    };
+    let mut value = json!({
+        "role": msg.role.as_str(),
+        "content": content,
+    });
+    let Some(obj) = value.as_object_mut() else {
+        return value;
+    };
+
+    if let Some(call_id) = &msg.tool_call_id {
+        obj.insert("tool_call_id".to_string(), json!(call_id));
+    }
+
+    if !msg.tool_calls.is_empty() {
+        let calls = msg
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "id": call.call_id,
+                    "type": "function",
+                    "function": {"name": call.name, "arguments": call.arguments},
+                })
+            })
+            .collect();
+        obj.insert("tool_calls".to_string(), Value::Array(calls));
+    }
+
+    value
+}
+
+/// Map Chat Completions `finish_reason`. Responses calling tools always finish with `FunctionCall`
+fn finish_reason(reason: Option<&str>, has_tool_calls: bool) -> FinishReason {
+    match reason {
+        _ if has_tool_calls => FinishReason::FunctionCall,
+        Some("length") => FinishReason::Length,
+        Some("content_filter") => FinishReason::ContentFilter,
+        Some("tool_calls" | "function_call") => FinishReason::FunctionCall,
+        _ => FinishReason::Stop,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Completion {
+    #[serde(default, with = "ts_seconds")]
+    created: DateTime<Utc>,
+    model: String,
+    choices: Vec<CompletionChoice>,
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChoice {
+    message: CompletionMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionMessage {
+    role: Option<Role>,
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<CompletionToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionToolCall {
+    id: String,
+    function: CompletionFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionFunction {
+    name: String,
+    #[serde(default)]
+    arguments: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CompletionUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+    #[serde(default)]
+    total_tokens: u32,
+    #[serde(default)]
+    prompt_tokens_details: InputTokensDetails,
+    #[serde(default)]
+    completion_tokens_details: OutputTokensDetails,
+}
+
+impl From<CompletionUsage> for Usage {
+    fn from(usage: CompletionUsage) -> Self {
+        Usage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            cached_tokens: usage.prompt_tokens_details.cached_tokens,
+            reasoning_tokens: usage.completion_tokens_details.reasoning_tokens,
+        }
+    }
+}
+
+impl TryFrom<Completion> for Response {
+    type Error = String;
+
+    fn try_from(value: Completion) -> Result<Self, Self::Error> {
+        if value.choices.is_empty() {
+            return Err("response did not contain any choices".to_string());
+        }
+
+        let choices = value
+            .choices
+            .into_iter()
+            .map(|choice| {
+                let tool_calls = choice
+                    .message
+                    .tool_calls
+                    .into_iter()
+                    .map(|call| ToolCall {
+                        call_id: call.id,
+                        name: call.function.name,
+                        arguments: call.function.arguments,
+                    })
+                    .collect::<Vec<_>>();
+                let finish_reason =
+                    finish_reason(choice.finish_reason.as_deref(), !tool_calls.is_empty());
+                let message = Message {
+                    tool_calls,
+                    ..Message::new(
+                        choice.message.role.unwrap_or(Role::Assistant),
+                        choice.message.content.as_deref().unwrap_or_default(),
+                    )
+                };
+                Choice {
+                    message,
+                    finish_reason,
+                }
+            })
+            .collect();
+
+        Ok(Response {
+            id: None,
+            created: value.created,
+            choices,
+            model: value.model,
+            usage: value.usage.unwrap_or_default().into(),
+            cached: false,
+        })
+    }
+}
+
+/// Chunks streamed with `"stream": true`
+#[derive(Debug, Deserialize)]
+struct CompletionChunk {
+    #[serde(default, with = "ts_seconds")]
+    created: DateTime<Utc>,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChunkDelta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ChunkToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkToolCall {
+    #[serde(default)]
+    index: usize,
+    id: Option<String>,
+    function: Option<ChunkFunction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkFunction {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// Response accumulated from streamed chunks
+#[derive(Debug, Default)]
+struct StreamState {
+    created: DateTime<Utc>,
+    model: String,
+    content: String,
+    tool_calls: Vec<ToolCall>,
+    finish_reason: Option<String>,
+    usage: Option<CompletionUsage>,
+}
+
+impl StreamState {
+    /// Accumulate chunk, returning text delta if any
+    fn push(&mut self, chunk: CompletionChunk) -> Option<String> {
+        self.created = chunk.created;
+        if !chunk.model.is_empty() {
+            self.model = chunk.model;
+        }
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+
+        let choice = chunk.choices.into_iter().next()?;
+        if choice.finish_reason.is_some() {
+            self.finish_reason = choice.finish_reason;
+        }
+
+        for call in choice.delta.tool_calls {
+            if self.tool_calls.len() <= call.index {
+                self.tool_calls.resize_with(call.index + 1, || ToolCall {
+                    call_id: String::new(),
+                    name: String::new(),
+                    arguments: String::new(),
+                });
+            }
+            let entry = &mut self.tool_calls[call.index];
+            if let Some(id) = call.id {
+                entry.call_id = id;
+            }
+            if let Some(function) = call.function {
+                entry.name.push_str(&function.name.unwrap_or_default());
+                entry
+                    .arguments
+                    .push_str(&function.arguments.unwrap_or_default());
+            }
+        }
+
+        let delta = choice.delta.content.filter(|c| !c.is_empty())?;
+        self.content.push_str(&delta);
+        Some(delta)
+    }
+
+    /// Build final response, emitting `StreamEvent::Completed`
+    fn finish(self, on_event: &mut OnEvent<'_>) -> Result<Response, Error> {
+        let finish_reason =
+            finish_reason(self.finish_reason.as_deref(), !self.tool_calls.is_empty());
+        let response = Response {
+            id: None,
+            created: self.created,
+            choices: vec![Choice {
+                message: Message {
+                    tool_calls: self.tool_calls,
+                    ..Message::assistant(&self.content)
+                },
+                finish_reason,
+            }],
+            model: self.model,
+            usage: self.usage.unwrap_or_default().into(),
+            cached: false,
+        };
+        on_event(&StreamEvent::Completed(response.clone()))?;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::attachment::Attachment;
+    use crate::openai::{Reasoning, ReasoningEffort, Tool};
+
+    use anyhow::Result;
+
+    #[test]
+    fn payload_for_request() {
+        let request = ResponseRequest::builder()
+            .model("llama3".to_string())
+            .messages(vec![
+                Message::system("SYSTEM"),
+                Message::tool_calls(vec![ToolCall {
+                    call_id: "call_1".to_string(),
+                    name: "get_time".to_string(),
+                    arguments: "{}".to_string(),
+                }]),
+                Message::tool("call_1", "12:00"),
+            ])
+            .temperature(Some(0.5))
+            .timeout(Duration::from_secs(30))
+            .reasoning(Some(Reasoning::from_effort(ReasoningEffort::Low)))
+            .text_format(Some(TextFormat::JsonObject))
+            .tools(vec![Tool {
+                name: "get_time".to_string(),
+                description: None,
+                parameters: json!({"type": "object"}),
+                strict: None,
+            }])
+            .build()
+            .expect("request builds");
+
+        assert_eq!(
+            to_payload(&request),
+            json!({
+                "model": "llama3",
+                "messages": [
+                    {"role": "system", "content": "SYSTEM"},
+                    {"role": "assistant", "content": "", "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "get_time", "arguments": "{}"},
+                    }]},
+                    {"role": "tool", "content": "12:00", "tool_call_id": "call_1"},
+                ],
+                "temperature": 0.5,
+                "reasoning_effort": "low",
+                "response_format": {"type": "json_object"},
+                "tools": [{
+                    "type": "function",
+                    "function": {"name": "get_time", "parameters": {"type": "object"}},
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn message_with_attachments() {
+        let msg = Message {
+            attachments: vec![
+                Attachment {
+                    kind: AttachmentKind::Image,
+                    source: "cat.png".to_string(),
+                    url: "data:image/png;base64,iVBORw==".to_string(),
+                },
+                Attachment {
+                    kind: AttachmentKind::File,
+                    source: "docs/report.pdf".to_string(),
+                    url: "data:application/pdf;base64,aGk=".to_string(),
+                },
+            ],
+            ..Message::user("Compare")
+        };
+
+        assert_eq!(
+            to_message(&msg),
+            json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "Compare"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw=="}},
+                    {"type": "file", "file": {"filename": "report.pdf", "file_data": "data:application/pdf;base64,aGk="}},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn response_from_completion() -> Result<()> {
+        let data = r#"{
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1688413145,
+            "model": "llama3",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_time", "arguments": "{}"}
+                }]},
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {
+                "prompt_tokens": 5,
+                "completion_tokens": 7,
+                "total_tokens": 12,
+                "prompt_tokens_details": {"cached_tokens": 3},
+                "completion_tokens_details": {"reasoning_tokens": 4}
+            }
+        }"#;
+
+        let resp = Response::try_from(serde_json::from_str::<Completion>(data)?)
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        assert_eq!(resp.model, "llama3");
+        assert_eq!(
+            resp.usage,
+            Usage {
+                input_tokens: 5,
+                output_tokens: 7,
+                total_tokens: 12,
+                cached_tokens: 3,
+                reasoning_tokens: 4,
+            }
+        );
+        assert_eq!(
+            resp.choices,
+            vec![Choice {
+                message: Message::tool_calls(vec![ToolCall {
+                    call_id: "call_1".to_string(),
+                    name: "get_time".to_string(),
+                    arguments: "{}".to_string(),
+                }]),
+                finish_reason: FinishReason::FunctionCall,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stream_state_accumulates_chunks() -> Result<()> {
+        let chunks = [
+            r#"{"created": 1, "model": "llama3", "choices": [{"delta": {"role": "assistant", "content": "Hel"}}]}"#,
+            r#"{"created": 1, "model": "llama3", "choices": [{"delta": {"content": "lo"}}]}"#,
+            r#"{"created": 1, "model": "llama3", "choices": [{"delta": {"tool_calls": [{"index": 0, "id": "call_1", "function": {"name": "get_time", "arguments": "{\"tz\":"}}]}}]}"#,
+            r#"{"created": 1, "model": "llama3", "choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "\"UTC\"}"}}]}, "finish_reason": "tool_calls"}]}"#,
+            r#"{"created": 1, "model": "llama3", "choices": [], "usage": {"prompt_tokens": 1, "completion_tokens": 2, "total_tokens": 3}}"#,
+        ];
+
+        let mut state = StreamState::default();
+        let deltas = chunks
+            .iter()
+            .map(|c| Ok(state.push(serde_json::from_str(c)?)))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            deltas,
+            vec![
+                Some("Hel".to_string()),
+                Some("lo".to_string()),
+                None,
+                None,
+                None
+            ]
+        );
+
+        let mut completed = 0;
+        let resp = state.finish(&mut |event| {
+            if let StreamEvent::Completed(_) = event {
+                completed += 1;
+            }
+            Ok(())
+        })?;
+
+        assert_eq!(completed, 1);
+        assert_eq!(resp.usage.total_tokens, 3);
+        assert_eq!(
+            resp.choices,
+            vec![Choice {
+                message: Message {
+                    tool_calls: vec![ToolCall {
+                        call_id: "call_1".to_string(),
+                        name: "get_time".to_string(),
+                        arguments: r#"{"tz":"UTC"}"#.to_string(),
+                    }],
+                    ..Message::assistant("Hello")
+                },
+                finish_reason: FinishReason::FunctionCall,
+            }]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+//! Provider replying from a rules file without any network access, for scripting and tests
+//!
+//! ```toml
+//! [[rules]]
+//! pattern = "(?i)weather in (\\w+)"
+//! reply = "It is sunny in $1"
+//!
+//! [[rules]]
+//! pattern = "essay"
+//! reply = "Once upon a time"
+//! finish_reason = "length"
+//! usage = { input_tokens = 12, output_tokens = 4 }
+//! ```
+//!
+//! Rules are tried in order against the last user message, and the first matching rule replies.
+//! Replies may reference captures of the pattern, e.g. `$1`. When no rule matches, the last user
+//! message is echoed.
+
+use std::path::Path;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::Error;
+use crate::openai::{Choice, FinishReason, Message, Response, ResponseRequest, Role, Usage};
+use crate::provider::Provider;
+
+/// Client replying per rules, echoing input if no rule matches
+#[derive(Debug, Default)]
+pub struct Client {
+    rules: Vec<Rule>,
+}
+
+/// Contents of rules file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rules: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    pattern: String,
+    reply: String,
+    finish_reason: Option<FinishReason>,
+    usage: Option<RuleUsage>,
+}
+
+/// Token counts reported for rule's reply
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    cached_tokens: u32,
+    #[serde(default)]
+    reasoning_tokens: u32,
+}
+
+#[derive(Debug)]
+struct Rule {
+    pattern: Regex,
+    reply: String,
+    finish_reason: Option<FinishReason>,
+    usage: Option<RuleUsage>,
+}
+
+impl Client {
+    /// Client with rules from file at `path`. Empty `path` echoes every input
+    pub fn new(path: &str) -> Result<Self, Error> {
+        if path.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(Path::new(path))
+            .map_err(|e| Error::InvalidMockRules(format!("{path} - {e}")))?;
+        Self::parse(&content).map_err(|e| match e {
+            Error::InvalidMockRules(reason) => {
+                Error::InvalidMockRules(format!("{path} - {reason}"))
+            }
+            e => e,
+        })
+    }
+
+    /// Client with rules parsed from TOML `content`
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        let file: RulesFile =
+            toml::from_str(content).map_err(|e| Error::InvalidMockRules(e.to_string()))?;
+
+        let rules = file
+            .rules
+            .into_iter()
+            .enumerate()
+            .map(|(idx, rule)| {
+                let pattern = Regex::new(&rule.pattern)
+                    .map_err(|e| Error::InvalidMockRules(format!("rule {} - {e}", idx + 1)))?;
+                Ok(Rule {
+                    pattern,
+                    reply: rule.reply,
+                    finish_reason: rule.finish_reason,
+                    usage: rule.usage,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Reply to `input`, with finish reason and usage of matching rule if any
+    fn reply(&self, input: &str) -> (String, Option<FinishReason>, Option<RuleUsage>) {
+        for rule in &self.rules {
+            if let Some(captures) = rule.pattern.captures(input) {
+                let mut reply = String::new();
+                captures.expand(&rule.reply, &mut reply);
+                return (reply, rule.finish_reason.clone(), rule.usage);
+            }
+        }
+        (input.to_string(), None, None)
+    }
+}
+
+/// Approximate token count of `text`, as number of words
+fn count_tokens(text: &str) -> u32 {
+    text.split_whitespace().count() as u32
+}
+
+#[async_trait]
+impl Provider for Client {
+    async fn create_response(&self, request: &ResponseRequest) -> Result<Response, Error> {
+        let input = request
+            .messages
+            .iter()
+            .rev()
+            .find(|msg| msg.role == Role::User)
+            .map(|msg| msg.content.as_str())
+            .unwrap_or_default();
+
+        let (mut reply, finish_reason, usage) = self.reply(input);
+        let mut finish_reason = finish_reason.unwrap_or(FinishReason::Stop);
+
+        // Truncate to output token limit, as a model would
+        if let Some(max) = request.max_output_tokens
+            && count_tokens(&reply) > max
+        {
+            reply = reply
+                .split_whitespace()
+                .take(max as usize)
+                .collect::<Vec<_>>()
+                .join(" ");
+            finish_reason = FinishReason::Length;
+        }
+
+        let usage = usage.unwrap_or_else(|| RuleUsage {
+            input_tokens: request
+                .messages
+                .iter()
+                .map(|m| count_tokens(&m.content))
+                .sum(),
+            output_tokens: count_tokens(&reply),
+            cached_tokens: 0,
+            reasoning_tokens: 0,
+        });
+
+        Ok(Response {
+            id: None,
+            created: Utc::now(),
+            choices: vec![Choice {
+                message: Message::assistant(&reply),
+                finish_reason,
+            }],
+            model: request.model.clone(),
+            usage: Usage {
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                total_tokens: usage.input_tokens + usage.output_tokens,
+                cached_tokens: usage.cached_tokens,
+                reasoning_tokens: usage.reasoning_tokens,
+            },
+            cached: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn request(content: &str) -> ResponseRequest {
+        ResponseRequest::builder()
+            .model("mock-1".to_string())
+            .messages(vec![Message::system("Be brief"), Message::user(content)])
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("request builds")
+    }
+
+    const RULES: &str = r#"
+[[rules]]
+pattern = "(?i)weather in (\\w+)"
+reply = "It is sunny in $1"
+
+[[rules]]
+pattern = "essay"
+reply = "Once upon a time"
+finish_reason = "length"
+usage = { input_tokens = 12, output_tokens = 4 }
+"#;
+
+    #[tokio::test]
+    async fn reply_from_matching_rule() -> Result<(), Error> {
+        let client = Client::parse(RULES)?;
+
+        let res = client
+            .create_response(&request("What's the Weather in Tokyo?"))
+            .await?;
+        assert_eq!(
+            res.choices[0].message,
+            Message::assistant("It is sunny in Tokyo")
+        );
+        assert_eq!(res.choices[0].finish_reason, FinishReason::Stop);
+        assert_eq!(res.model, "mock-1");
+        assert_eq!(
+            res.usage,
+            Usage {
+                input_tokens: 7,
+                output_tokens: 5,
+                total_tokens: 12,
+                ..Usage::default()
+            }
+        );
+
+        let res = client.create_response(&request("Write an essay")).await?;
+        assert_eq!(
+            res.choices[0].message,
+            Message::assistant("Once upon a time")
+        );
+        assert_eq!(res.choices[0].finish_reason, FinishReason::Length);
+        assert_eq!(res.usage.total_tokens, 16);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn echo_without_matching_rule() -> Result<(), Error> {
+        let client = Client::parse(RULES)?;
+        let res = client.create_response(&request("Hello there")).await?;
+        assert_eq!(res.choices[0].message, Message::assistant("Hello there"));
+        assert_eq!(res.choices[0].finish_reason, FinishReason::Stop);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn truncate_to_max_output_tokens() -> Result<(), Error> {
+        let mut request = request("one two three four");
+        request.max_output_tokens = Some(2);
+
+        let res = Client::default().create_response(&request).await?;
+        assert_eq!(res.choices[0].message, Message::assistant("one two"));
+        assert_eq!(res.choices[0].finish_reason, FinishReason::Length);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let err =
+            Client::parse("[[rules]]\npattern = \"(\"\nreply = \"\"\n").expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidMockRules(reason) if reason.starts_with("rule 1")),
+            "unexpected error: {err:?}"
+        );
+    }
+}
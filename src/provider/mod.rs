@@ -0,0 +1,237 @@
+//! Backends that requests can be sent to
+
+pub mod anthropic;
+pub mod chat_completions;
+pub mod mock;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Error;
+use crate::cassette;
+use crate::openai::{self, Response, ResponseRequest, StreamEvent};
+use crate::retry::RetryPolicy;
+
+/// Callback invoked for each event while streaming
+pub type OnEvent<'a> = dyn FnMut(&StreamEvent) -> Result<(), Error> + Send + 'a;
+
+/// Backend that creates responses for requests
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Create response for request
+    async fn create_response(&self, request: &ResponseRequest) -> Result<Response, Error>;
+
+    /// Create response for request, invoking `on_event` as output is generated.
+    /// Providers without streaming emit the complete text once the response is available.
+    async fn stream_response(
+        &self,
+        request: &ResponseRequest,
+        on_event: &mut OnEvent<'_>,
+    ) -> Result<Response, Error> {
+        let response = self.create_response(request).await?;
+        replay(&response, on_event)?;
+        Ok(response)
+    }
+}
+
+/// Emit events for complete `response`, as if it were streamed in one delta
+pub(crate) fn replay(response: &Response, on_event: &mut OnEvent<'_>) -> Result<(), Error> {
+    for choice in &response.choices {
+        if !choice.message.content.is_empty() {
+            on_event(&StreamEvent::OutputTextDelta(
+                choice.message.content.clone(),
+            ))?;
+        }
+    }
+    on_event(&StreamEvent::Completed(response.clone()))
+}
+
+/// Kinds of providers that can be selected via `--provider` or model prefix, e.g. `ollama:llama3`
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum ProviderKind {
+    /// OpenAI Responses API
+    #[default]
+    OpenAI,
+    /// OpenAI-compatible Chat Completions API, e.g. llama.cpp or vLLM servers
+    ChatCompletions,
+    /// Ollama's OpenAI-compatible Chat Completions API
+    Ollama,
+    /// Anthropic Messages API
+    Anthropic,
+    /// Canned replies from `mock_rules` file, without network access
+    Mock,
+}
+
+impl ProviderKind {
+    /// Name used in `--provider` and model prefixes
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::OpenAI => "openai",
+            ProviderKind::ChatCompletions => "chat-completions",
+            ProviderKind::Ollama => "ollama",
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::Mock => "mock",
+        }
+    }
+
+    /// Parse provider from name, as used in `--provider` and model prefixes
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            ProviderKind::OpenAI,
+            ProviderKind::ChatCompletions,
+            ProviderKind::Ollama,
+            ProviderKind::Anthropic,
+            ProviderKind::Mock,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == name)
+    }
+
+    /// Split provider prefix from `model`, e.g. `ollama:llama3:8b` into `Ollama` and `llama3:8b`.
+    /// Models without a known prefix are returned as is.
+    pub fn split_model(model: &str) -> (Option<Self>, &str) {
+        match model.split_once(':') {
+            Some((prefix, rest)) => match Self::from_name(prefix) {
+                Some(kind) => (Some(kind), rest),
+                None => (None, model),
+            },
+            None => (None, model),
+        }
+    }
+
+    /// Environment variable for API key of provider, other than `OPENAI_API_KEY` read by `--apikey`.
+    /// Ollama and chat completions servers are often third-party hosts, so they must never receive
+    /// `OPENAI_API_KEY`
+    pub fn api_key_env(&self) -> Option<&'static str> {
+        match self {
+            ProviderKind::ChatCompletions => Some("CHAT_COMPLETIONS_API_KEY"),
+            ProviderKind::Ollama => Some("OLLAMA_API_KEY"),
+            ProviderKind::Anthropic => Some("ANTHROPIC_API_KEY"),
+            _ => None,
+        }
+    }
+
+    /// Environment variable for base URL of provider's API endpoint, and the default base URL.
+    /// `None` for `ProviderKind::Mock`, which has no API
+    fn endpoint_defaults(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            ProviderKind::OpenAI | ProviderKind::ChatCompletions => {
+                Some(("OPENAI_API_ENDPOINT", "https://api.openai.com"))
+            }
+            ProviderKind::Ollama => Some(("OLLAMA_HOST", "http://localhost:11434")),
+            ProviderKind::Anthropic => {
+                Some(("ANTHROPIC_API_ENDPOINT", "https://api.anthropic.com"))
+            }
+            ProviderKind::Mock => None,
+        }
+    }
+}
+
+/// Base URL for provider of `kind`, or `None` for `ProviderKind::Mock`. The provider's endpoint
+/// environment variable takes precedence over `configured`, which takes precedence over the
+/// provider's default
+pub fn endpoint(kind: ProviderKind, configured: Option<&str>) -> Option<String> {
+    let (env, default) = kind.endpoint_defaults()?;
+    let base_url = std::env::var(env)
+        .ok()
+        .or(configured.map(str::to_string))
+        .unwrap_or(default.to_string());
+
+    // Hosts without scheme are common for OLLAMA_HOST, e.g. `127.0.0.1:11434`
+    match base_url.contains("://") {
+        true => Some(base_url),
+        false => Some(format!("http://{base_url}")),
+    }
+}
+
+/// Create provider of `kind`, with base URL per `endpoint`. `ProviderKind::Mock` replies per rules
+/// file at `mock_rules`, echoing input if there is none. Interactions are recorded or replayed per
+/// `COGNI_RECORD` and `COGNI_REPLAY`, which are only supported by `ProviderKind::OpenAI`
+pub fn create(
+    kind: ProviderKind,
+    api_key: Option<String>,
+    configured_endpoint: Option<&str>,
+    mock_rules: Option<&str>,
+    retry_policy: RetryPolicy,
+) -> Result<Box<dyn Provider>, Error> {
+    // Empty for mock provider, which doesn't use it
+    let base_url = endpoint(kind, configured_endpoint).unwrap_or_default();
+    let cassette = cassette::from_env()?;
+    if cassette.is_some() && kind != ProviderKind::OpenAI {
+        return Err(Error::InvalidCassette(format!(
+            "recording and replaying is not supported by {} provider",
+            kind.as_str()
+        )));
+    }
+
+    let provider: Box<dyn Provider> = match kind {
+        ProviderKind::OpenAI => Box::new(
+            openai::Client::new(api_key, base_url)?
+                .with_retry_policy(retry_policy)
+                .with_cassette(cassette),
+        ),
+        ProviderKind::ChatCompletions | ProviderKind::Ollama => Box::new(
+            chat_completions::Client::new(api_key, base_url)?.with_retry_policy(retry_policy),
+        ),
+        ProviderKind::Anthropic => {
+            Box::new(anthropic::Client::new(api_key, base_url)?.with_retry_policy(retry_policy))
+        }
+        ProviderKind::Mock => Box::new(mock::Client::new(mock_rules.unwrap_or_default())?),
+    };
+    Ok(provider)
+}
+
+impl Serialize for ProviderKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ProviderKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::from_name(&name).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "unknown provider \"{name}\", expected one of openai, chat-completions, ollama, anthropic, mock"
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for openai::Client {
+    async fn create_response(&self, request: &ResponseRequest) -> Result<Response, Error> {
+        openai::Client::create_response(self, request).await
+    }
+
+    async fn stream_response(
+        &self,
+        request: &ResponseRequest,
+        on_event: &mut OnEvent<'_>,
+    ) -> Result<Response, Error> {
+        openai::Client::stream_response(self, request, on_event).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_model_prefix() {
+        assert_eq!(
+            ProviderKind::split_model("ollama:llama3:8b"),
+            (Some(ProviderKind::Ollama), "llama3:8b")
+        );
+        assert_eq!(
+            ProviderKind::split_model("chat-completions:qwen"),
+            (Some(ProviderKind::ChatCompletions), "qwen")
+        );
+        assert_eq!(
+            ProviderKind::split_model("anthropic:claude-sonnet-4-5"),
+            (Some(ProviderKind::Anthropic), "claude-sonnet-4-5")
+        );
+        assert_eq!(ProviderKind::split_model("gpt-5"), (None, "gpt-5"));
+        assert_eq!(ProviderKind::split_model("llama3:8b"), (None, "llama3:8b"));
+    }
+}
@@ -0,0 +1,337 @@
+//! Retries with exponential backoff for transient API failures
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+
+use crate::Error;
+
+/// Policy for retrying rate limited, server error, connection, and timed out failures
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the initial attempt
+    pub max_retries: u32,
+    /// Delay before first retry, doubled for each subsequent retry
+    pub initial_backoff: Duration,
+    /// Upper bound on delay between attempts, including delays requested by response headers
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Exponential backoff before the given retry, starting at 1
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Send request created by `build`, retrying transient failures per `policy`.
+/// Returns final response, which may have a non-success status, along with the number of attempts made.
+/// Timed out requests may still have been processed, so their retries can duplicate generations
+pub(crate) async fn send(
+    policy: &RetryPolicy,
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<(reqwest::Response, u32), Error> {
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        let retry = attempts;
+        let can_retry = retry <= policy.max_retries;
+
+        match build().send().await {
+            Ok(resp) if can_retry && is_retryable(resp.status()) => {
+                let delay = delay_from_headers(resp.status(), resp.headers())
+                    .unwrap_or_else(|| policy.backoff(retry))
+                    .min(policy.max_backoff);
+                log::warn!(
+                    "request failed with status {}, retrying in {:?} (retry {} of {})",
+                    resp.status(),
+                    delay,
+                    retry,
+                    policy.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Ok(resp) => return Ok((resp, attempts)),
+            Err(e) if can_retry && e.is_connect() => {
+                let delay = policy.backoff(retry);
+                log::warn!(
+                    "request failed - {}, retrying in {:?} (retry {} of {})",
+                    e,
+                    delay,
+                    retry,
+                    policy.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) if can_retry && e.is_timeout() => {
+                let delay = policy.backoff(retry);
+                log::warn!(
+                    "request timed out - {}, retrying in {:?} (retry {} of {}). Server may still process timed out request, which is billed separately",
+                    e,
+                    delay,
+                    retry,
+                    policy.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(exhausted(Error::from(e), attempts)),
+        }
+    }
+}
+
+/// Wrap error with number of attempts made, if request was retried
+pub(crate) fn exhausted(error: Error, attempts: u32) -> Error {
+    if attempts > 1 {
+        Error::RetriesExhausted {
+            attempts,
+            source: Box::new(error),
+        }
+    } else {
+        error
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    ) || status.as_u16() == OVERLOADED
+}
+
+/// Non-standard status for overloaded servers, used by Anthropic
+const OVERLOADED: u16 = 529;
+
+/// Delay requested by `retry-after-ms`, `retry-after` in seconds or as HTTP-date, or
+/// `x-ratelimit-reset-*` headers. Reset
+/// headers describe quota rather than server recovery, so they are only used for rate limited
+/// `status`
+fn delay_from_headers(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.trim().parse::<f64>().ok()) {
+        return Duration::try_from_secs_f64(ms / 1000.0).ok();
+    }
+
+    if let Some(value) = header("retry-after").map(str::trim) {
+        if let Ok(secs) = value.parse::<f64>() {
+            return Duration::try_from_secs_f64(secs).ok();
+        }
+        if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+            // Dates in the past mean retrying right away
+            return Some(
+                (date.to_utc() - chrono::Utc::now())
+                    .to_std()
+                    .unwrap_or_default(),
+            );
+        }
+    }
+
+    if status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    ["x-ratelimit-reset-requests", "x-ratelimit-reset-tokens"]
+        .into_iter()
+        .filter_map(|name| header(name).and_then(parse_reset_duration))
+        .max()
+}
+
+/// Parse durations in the form of `x-ratelimit-reset-*` headers, e.g. `1s`, `6m0s`, `20ms`, `1h2m3.5s`
+fn parse_reset_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value.trim();
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let num_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let num = rest[..num_len].parse::<f64>().ok()?;
+        rest = &rest[num_len..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let scale = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+
+        total += num * scale;
+    }
+
+    Duration::try_from_secs_f64(total).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(3),
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(4), Duration::from_secs(3));
+        assert_eq!(policy.backoff(40), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn parse_reset_durations() {
+        assert_eq!(parse_reset_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(
+            parse_reset_duration("20ms"),
+            Some(Duration::from_millis(20))
+        );
+        assert_eq!(parse_reset_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(
+            parse_reset_duration("1h2m3.5s"),
+            Some(Duration::from_secs_f64(3723.5))
+        );
+        assert_eq!(parse_reset_duration(""), None);
+        assert_eq!(parse_reset_duration("soon"), None);
+        assert_eq!(parse_reset_duration("5d"), None);
+    }
+
+    #[test]
+    fn delay_prefers_retry_after_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-reset-requests", HeaderValue::from_static("2s"));
+        headers.insert("x-ratelimit-reset-tokens", HeaderValue::from_static("6s"));
+        assert_eq!(
+            delay_from_headers(StatusCode::TOO_MANY_REQUESTS, &headers),
+            Some(Duration::from_secs(6))
+        );
+
+        headers.insert("retry-after", HeaderValue::from_static("3"));
+        assert_eq!(
+            delay_from_headers(StatusCode::TOO_MANY_REQUESTS, &headers),
+            Some(Duration::from_secs(3))
+        );
+
+        headers.insert("retry-after-ms", HeaderValue::from_static("150"));
+        assert_eq!(
+            delay_from_headers(StatusCode::TOO_MANY_REQUESTS, &headers),
+            Some(Duration::from_millis(150))
+        );
+    }
+
+    #[test]
+    fn delay_from_retry_after_date() {
+        let mut headers = HeaderMap::new();
+        let date = (chrono::Utc::now() + chrono::TimeDelta::seconds(120)).to_rfc2822();
+        headers.insert("retry-after", HeaderValue::from_str(&date).unwrap());
+        let delay = delay_from_headers(StatusCode::SERVICE_UNAVAILABLE, &headers).unwrap();
+        assert!(
+            delay > Duration::from_secs(110) && delay <= Duration::from_secs(120),
+            "unexpected delay: {delay:?}"
+        );
+
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"),
+        );
+        assert_eq!(
+            delay_from_headers(StatusCode::SERVICE_UNAVAILABLE, &headers),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn delay_of_server_error_ignores_reset_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-reset-tokens", HeaderValue::from_static("6s"));
+        assert_eq!(
+            delay_from_headers(StatusCode::SERVICE_UNAVAILABLE, &headers),
+            None
+        );
+
+        headers.insert("retry-after", HeaderValue::from_static("3"));
+        assert_eq!(
+            delay_from_headers(StatusCode::SERVICE_UNAVAILABLE, &headers),
+            Some(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn delay_without_headers() {
+        assert_eq!(
+            delay_from_headers(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn send_retries_timeouts() -> Result<(), Box<dyn std::error::Error>> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            // First attempt never gets a response
+            let (_stalled, _) = listener.accept().await?;
+            let (mut stream, _) = listener.accept().await?;
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await?;
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                .await?;
+            std::io::Result::Ok(())
+        });
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()?;
+        let policy = RetryPolicy {
+            max_retries: 1,
+            initial_backoff: Duration::from_millis(10),
+            ..RetryPolicy::default()
+        };
+
+        let (resp, attempts) = send(&policy, || client.get(&url)).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(attempts, 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+//! Structured output via JSON Schema
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::Error;
+use crate::openai::TextFormat;
+
+/// Load JSON Schema at `path` as a strict `TextFormat::JsonSchema`, named after the file stem
+pub fn load(path: &str) -> Result<TextFormat, Error> {
+    let content = std::fs::read_to_string(path)?;
+    let schema = serde_json::from_str::<Value>(&content)?;
+
+    jsonschema::validator_for(&schema).map_err(|e| Error::InvalidSchema(e.to_string()))?;
+
+    Ok(TextFormat::JsonSchema {
+        name: schema_name(path),
+        schema,
+        strict: true,
+    })
+}
+
+/// Validate `text` is JSON that adheres to `format`
+pub fn validate(format: &TextFormat, text: &str) -> Result<(), Error> {
+    let instance = serde_json::from_str::<Value>(text).map_err(|e| Error::SchemaValidation {
+        path: "/".to_string(),
+        message: format!("output is not valid JSON - {e}"),
+    })?;
+
+    match format {
+        TextFormat::JsonObject if instance.is_object() => Ok(()),
+        TextFormat::JsonObject => Err(Error::SchemaValidation {
+            path: "/".to_string(),
+            message: "output is not a JSON object".to_string(),
+        }),
+        TextFormat::JsonSchema { schema, .. } => {
+            let validator = jsonschema::validator_for(schema)
+                .map_err(|e| Error::InvalidSchema(e.to_string()))?;
+
+            match validator.iter_errors(&instance).next() {
+                None => Ok(()),
+                Some(e) => {
+                    let path = e.instance_path().to_string();
+                    Err(Error::SchemaValidation {
+                        path: if path.is_empty() {
+                            "/".to_string()
+                        } else {
+                            path
+                        },
+                        message: e.to_string(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Schema name from file stem, restricted to characters accepted by the API
+fn schema_name(path: &str) -> String {
+    let name = Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect::<String>();
+
+    if name.is_empty() {
+        "schema".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::prelude::*;
+    use serde_json::json;
+
+    fn event_schema() -> TextFormat {
+        TextFormat::JsonSchema {
+            name: "event".to_string(),
+            schema: json!({
+                "type": "object",
+                "properties": {
+                    "level": { "type": "string", "enum": ["info", "error"] },
+                    "tags": { "type": "array", "items": { "type": "string" } }
+                },
+                "required": ["level"],
+                "additionalProperties": false
+            }),
+            strict: true,
+        }
+    }
+
+    #[test]
+    fn load_schema_file() {
+        let file = assert_fs::NamedTempFile::new("log event.json").unwrap();
+        file.write_str(r#"{"type": "object"}"#).unwrap();
+
+        let format = load(file.path().to_str().unwrap()).expect("should load");
+
+        assert_eq!(
+            format,
+            TextFormat::JsonSchema {
+                name: "log_event".to_string(),
+                schema: json!({"type": "object"}),
+                strict: true,
+            }
+        );
+    }
+
+    #[test]
+    fn load_invalid_schema() {
+        let file = assert_fs::NamedTempFile::new("bad.json").unwrap();
+        file.write_str(r#"{"type": 42}"#).unwrap();
+
+        let err = load(file.path().to_str().unwrap()).expect_err("should fail");
+        assert!(
+            matches!(err, Error::InvalidSchema(_)),
+            "unexpected: {err:?}"
+        );
+    }
+
+    #[test]
+    fn validate_matching_output() {
+        let res = validate(&event_schema(), r#"{"level": "info", "tags": ["db"]}"#);
+        assert!(res.is_ok(), "unexpected: {res:?}");
+    }
+
+    #[test]
+    fn validate_reports_path() {
+        let err = validate(&event_schema(), r#"{"level": "info", "tags": ["db", 3]}"#)
+            .expect_err("should fail");
+
+        assert!(
+            matches!(&err, Error::SchemaValidation { path, .. } if path == "/tags/1"),
+            "unexpected: {err:?}"
+        );
+    }
+
+    #[test]
+    fn validate_invalid_json() {
+        let err = validate(&event_schema(), "level: info").expect_err("should fail");
+        assert!(
+            matches!(&err, Error::SchemaValidation { path, .. } if path == "/"),
+            "unexpected: {err:?}"
+        );
+    }
+
+    #[test]
+    fn validate_json_object() {
+        assert!(validate(&TextFormat::JsonObject, r#"{"a": 1}"#).is_ok());
+        assert!(validate(&TextFormat::JsonObject, "[1, 2]").is_err());
+    }
+}
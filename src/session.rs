@@ -0,0 +1,243 @@
+//! Named conversations persisted across invocations
+//!
+//! Sessions are stored as JSON under `$XDG_STATE_HOME/cogni/sessions/`, with the message history,
+//! the model settings of the last request, and token usage of all requests.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+use crate::config::Settings;
+use crate::openai::{Message, Role, Usage};
+
+/// Extension of session files
+const EXTENSION: &str = "json";
+
+/// Stored conversation
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Settings used for the last request, excluding `system` which is part of `messages`
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub messages: Vec<Message>,
+    /// Token usage summed over requests in session
+    #[serde(default)]
+    pub usage: Usage,
+}
+
+/// Directory sessions are stored in, `cogni/sessions` under `$XDG_STATE_HOME` or `~/.local/state`
+pub fn dir() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+
+    Some(state_home.join("cogni").join("sessions"))
+}
+
+/// Path to file of session `name`
+fn path(name: &str) -> Result<PathBuf, Error> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(Error::InvalidSession(format!(
+            "\"{name}\" is not a valid name. Use letters, digits, '-', '_', or '.'"
+        )));
+    }
+
+    let dir = dir().ok_or_else(|| {
+        Error::InvalidSession("neither XDG_STATE_HOME nor HOME is set".to_string())
+    })?;
+    Ok(dir.join(format!("{name}.{EXTENSION}")))
+}
+
+/// Load session `name`. `None` if session does not exist yet
+pub fn load(name: &str) -> Result<Option<Session>, Error> {
+    let path = path(name)?;
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| Error::InvalidSession(format!("{} - {e}", path.display()))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::IO(e)),
+    }
+}
+
+/// Load session `name`, erroring if it doesn't exist
+pub fn load_existing(name: &str) -> Result<Session, Error> {
+    load(name)?.ok_or_else(|| Error::InvalidSession(format!("no session named \"{name}\"")))
+}
+
+/// Names of stored sessions, sorted
+pub fn list() -> Result<Vec<String>, Error> {
+    let Some(dir) = dir() else {
+        return Ok(vec![]);
+    };
+
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(Error::IO(e)),
+    };
+
+    let mut names = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == EXTENSION)
+            && let Some(name) = path.file_stem().and_then(|s| s.to_str())
+            && !name.starts_with('.')
+        {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Remove session `name`
+pub fn remove(name: &str) -> Result<(), Error> {
+    match std::fs::remove_file(path(name)?) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(Error::InvalidSession(format!(
+            "no session named \"{name}\""
+        ))),
+        res => Ok(res?),
+    }
+}
+
+/// Copy session `from` to new session `to`
+pub fn fork(from: &str, to: &str) -> Result<(), Error> {
+    let session = load_existing(from)?;
+    if path(to)?.exists() {
+        return Err(Error::InvalidSession(format!(
+            "session \"{to}\" already exists"
+        )));
+    }
+    session.save(to)
+}
+
+impl Session {
+    /// `settings` with session's settings taking precedence. Session's system prompt replaces
+    /// the one in `settings`
+    pub fn apply(&self, settings: Settings) -> Settings {
+        let mut settings = settings.merge(self.settings.clone());
+        if self.messages.iter().any(|m| m.role == Role::System) {
+            settings.system = None;
+        }
+        settings
+    }
+
+    /// Save session as `name`. Written to a temporary file that is renamed over the session file,
+    /// so concurrent invocations never observe a partially written session
+    pub fn save(&self, name: &str) -> Result<(), Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = path(name)?;
+        let dir = path.parent().expect("session path has parent");
+        std::fs::create_dir_all(dir)?;
+
+        let tmp = dir.join(format!(
+            ".{name}.{}.{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp, &path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })?;
+        Ok(())
+    }
+}
+
+/// Messages of `history` followed by `msgs`. A system prompt in `msgs` replaces the one in `history`
+pub fn with_history(history: &[Message], msgs: Vec<Message>) -> Vec<Message> {
+    let (system, msgs): (Vec<_>, Vec<_>) = msgs.into_iter().partition(|m| m.role == Role::System);
+    let replace_system = !system.is_empty();
+    let history = history
+        .iter()
+        .filter(|m| !replace_system || m.role != Role::System)
+        .cloned();
+
+    system.into_iter().chain(history).chain(msgs).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn history_before_messages() {
+        let history = vec![
+            Message::system("SYSTEM"),
+            Message::user("Hi"),
+            Message::assistant("Hello"),
+        ];
+
+        assert_eq!(
+            with_history(&history, vec![Message::user("Bye")]),
+            vec![
+                Message::system("SYSTEM"),
+                Message::user("Hi"),
+                Message::assistant("Hello"),
+                Message::user("Bye"),
+            ]
+        );
+    }
+
+    #[test]
+    fn history_system_prompt_replaced() {
+        let history = vec![Message::system("OLD"), Message::user("Hi")];
+
+        assert_eq!(
+            with_history(&history, vec![Message::system("NEW"), Message::user("Bye")]),
+            vec![
+                Message::system("NEW"),
+                Message::user("Hi"),
+                Message::user("Bye"),
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_session_settings() {
+        let session = Session {
+            settings: Settings {
+                model: Some("gpt-5-mini".to_string()),
+                ..Settings::default()
+            },
+            messages: vec![Message::system("SESSION")],
+            ..Session::default()
+        };
+        let settings = Settings {
+            model: Some("gpt-5".to_string()),
+            timeout: Some(120),
+            system: Some("CONFIG".to_string()),
+            ..Settings::default()
+        };
+
+        assert_eq!(
+            session.apply(settings),
+            Settings {
+                model: Some("gpt-5-mini".to_string()),
+                timeout: Some(120),
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in ["", ".hidden", "../escape", "a/b"] {
+            assert!(
+                matches!(path(name), Err(Error::InvalidSession(_))),
+                "{name:?} should be invalid"
+            );
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! Reusable prompt templates with `{{var}}` placeholders
+//!
+//! Templates have optional TOML front matter with the same settings as config profiles, followed
+//! by a transcript or text body:
+//!
+//! ```text
+//! ---
+//! model = "gpt-5-mini"
+//! system = "Write a commit message in {{style}} style"
+//! ---
+//! >>> user
+//! {{input}}
+//! ```
+//!
+//! Scripts are templates starting with a `#!/usr/bin/env cogni` line, so they can be run directly.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+use crate::attachment::LocalFiles;
+use crate::config::{self, Settings};
+use crate::openai::{Message, Role, ToolCall};
+use crate::parse::{self, InputFormat};
+
+/// Delimiter line surrounding front matter
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+/// Prefix of first line of scripts
+const SHEBANG: &str = "#!";
+
+/// Variable bound to stdin, or file given as argument
+pub const INPUT_VAR: &str = "input";
+
+/// Parsed template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// Settings from front matter, excluding `system` which is part of `messages`
+    pub settings: Settings,
+    /// Messages with placeholders
+    pub messages: Vec<Message>,
+}
+
+/// Directory that templates are discovered from by name. `$COGNI_TEMPLATES` if set, otherwise
+/// `templates` under the config directory
+pub fn dir() -> Option<PathBuf> {
+    match std::env::var_os("COGNI_TEMPLATES") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => config::dir().map(|dir| dir.join("templates")),
+    }
+}
+
+/// Load template at path `name`, or named `name` or `name.tmpl` in templates directory
+pub fn load(name: &str) -> Result<Template, Error> {
+    let path = find(name)?;
+    let content = std::fs::read_to_string(&path)?;
+    Template::parse(&content, path.parent().unwrap_or(Path::new("")))
+        .map_err(|e| Error::InvalidTemplate(format!("{} - {e}", path.display())))
+}
+
+/// Load script at `path`. `None` if `path` can't be read or doesn't start with `#!`
+pub fn load_script(path: &str) -> Result<Option<Template>, Error> {
+    let Ok(mut file) = File::open(path) else {
+        return Ok(None);
+    };
+
+    let mut prefix = [0; SHEBANG.len()];
+    if file.read_exact(&mut prefix).is_err() || prefix != SHEBANG.as_bytes() {
+        return Ok(None);
+    }
+
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let body = content.split_once('\n').map_or("", |(_, body)| body);
+
+    Template::parse(body, Path::new(path).parent().unwrap_or(Path::new("")))
+        .map(Some)
+        .map_err(|e| Error::InvalidTemplate(format!("{path} - {e}")))
+}
+
+fn find(name: &str) -> Result<PathBuf, Error> {
+    let path = Path::new(name);
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+
+    let candidates = dir()
+        .map(|dir| vec![dir.join(name), dir.join(format!("{name}.tmpl"))])
+        .unwrap_or_default();
+
+    candidates
+        .into_iter()
+        .find(|p| p.is_file())
+        .ok_or_else(|| Error::InvalidTemplate(format!("no template found for \"{name}\"")))
+}
+
+impl Template {
+    /// Parse template from front matter and body. Attachments of local files are resolved relative
+    /// to `dir`, the template's directory
+    pub fn parse(content: &str, dir: &Path) -> Result<Self, Error> {
+        let (front_matter, body) = split_front_matter(content)?;
+
+        let mut settings = match front_matter {
+            Some(front_matter) => toml::from_str::<Settings>(front_matter)
+                .map_err(|e| Error::InvalidTemplate(e.message().to_string()))?,
+            None => Settings::default(),
+        };
+
+        let local = LocalFiles::Allow(dir.to_path_buf());
+        let mut messages =
+            parse::parse_messages_with(&mut body.as_bytes(), InputFormat::Auto, &local)?;
+        if let Some(system) = settings.system.take() {
+            messages.insert(0, Message::system(&system));
+        }
+
+        Ok(Self { settings, messages })
+    }
+
+    /// `settings` with template's settings taking precedence. Template's system prompt replaces
+    /// the one in `settings`
+    pub fn apply(&self, settings: Settings) -> Settings {
+        let mut settings = settings.merge(self.settings.clone());
+        if self.messages.iter().any(|m| m.role == Role::System) {
+            settings.system = None;
+        }
+        settings
+    }
+
+    /// Names of variables referenced by placeholders
+    pub fn variables(&self) -> BTreeSet<&str> {
+        self.messages
+            .iter()
+            .flat_map(|m| {
+                let calls = m.tool_calls.iter().map(|c| c.arguments.as_str());
+                std::iter::once(m.content.as_str()).chain(calls)
+            })
+            .flat_map(placeholders)
+            .collect()
+    }
+
+    /// Messages with placeholders replaced by `vars`. Errors listing all variables that are missing
+    pub fn render(&self, vars: &BTreeMap<String, String>) -> Result<Vec<Message>, Error> {
+        let missing = self
+            .variables()
+            .into_iter()
+            .filter(|v| !vars.contains_key(*v))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(Error::MissingTemplateVariables(missing));
+        }
+
+        let render = |text: &str| replace_placeholders(text, |name| vars[name].clone());
+
+        Ok(self
+            .messages
+            .iter()
+            .map(|m| Message {
+                content: render(&m.content),
+                tool_calls: m
+                    .tool_calls
+                    .iter()
+                    .map(|c| ToolCall {
+                        arguments: render(&c.arguments),
+                        ..c.clone()
+                    })
+                    .collect(),
+                ..m.clone()
+            })
+            .collect())
+    }
+}
+
+/// Split `---` delimited front matter from body
+fn split_front_matter(content: &str) -> Result<(Option<&str>, &str), Error> {
+    let Some(rest) = content
+        .strip_prefix(FRONT_MATTER_DELIMITER)
+        .and_then(|r| r.strip_prefix('\n').or_else(|| r.strip_prefix("\r\n")))
+    else {
+        return Ok((None, content));
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == FRONT_MATTER_DELIMITER {
+            return Ok((Some(&rest[..offset]), &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+
+    Err(Error::InvalidTemplate(
+        "front matter is missing closing \"---\"".to_string(),
+    ))
+}
+
+/// Names in `{{name}}` placeholders of `text`
+pub(crate) fn placeholders(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let (_, end, name) = find_placeholder(rest)?;
+        rest = &rest[end..];
+        Some(name)
+    })
+}
+
+/// Replace `{{name}}` placeholders of `text` with `value(name)`. Substituted values are not
+/// scanned for placeholders.
+pub(crate) fn replace_placeholders(text: &str, mut value: impl FnMut(&str) -> String) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+
+    while let Some((start, end, name)) = find_placeholder(rest) {
+        output.push_str(&rest[..start]);
+        output.push_str(&value(name));
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// Start, end, and name of first placeholder in `text`. Names are letters, digits, `_`, or `-`,
+/// optionally padded by spaces. Any other `{{` is literal text.
+fn find_placeholder(text: &str) -> Option<(usize, usize, &str)> {
+    let mut from = 0;
+    while let Some(pos) = text[from..].find("{{") {
+        let start = from + pos;
+        let inner = &text[start + 2..];
+        if let Some(len) = inner.find("}}") {
+            let name = inner[..len].trim_matches([' ', '\t']);
+            if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Some((start, start + 2 + len + 2, name));
+            }
+        }
+        from = start + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    const TEMPLATE: &str = r#"---
+model = "gpt-5-mini"
+temperature = 0.2
+system = "Write a commit message in {{ style }} style"
+---
+>>> user
+{{input}}
+
+>>> assistant
+Understood, {{style}}.
+"#;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_template() {
+        let template = Template::parse(TEMPLATE, Path::new("")).expect("should parse");
+
+        assert_eq!(
+            template.settings,
+            Settings {
+                model: Some("gpt-5-mini".to_string()),
+                temperature: Some(0.2),
+                ..Settings::default()
+            }
+        );
+        assert_eq!(
+            template.messages,
+            vec![
+                Message::system("Write a commit message in {{ style }} style"),
+                Message::user("{{input}}"),
+                Message::assistant("Understood, {{style}}."),
+            ]
+        );
+        assert_eq!(template.variables(), BTreeSet::from([INPUT_VAR, "style"]));
+    }
+
+    #[test]
+    fn parse_template_without_front_matter() {
+        let template = Template::parse("Translate to {{lang}}:\n{{input}}", Path::new(""))
+            .expect("should parse");
+        assert_eq!(template.settings, Settings::default());
+        assert_eq!(
+            template.messages,
+            vec![Message::user("Translate to {{lang}}:\n{{input}}")]
+        );
+    }
+
+    #[test]
+    fn parse_template_unclosed_front_matter() {
+        let err = Template::parse("---\nmodel = \"gpt-5\"\n>>> user\nHi", Path::new(""))
+            .expect_err("should fail");
+        assert!(
+            matches!(err, Error::InvalidTemplate(_)),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn apply_template_settings() {
+        let template = Template::parse(TEMPLATE, Path::new("")).expect("should parse");
+        let settings = Settings {
+            model: Some("gpt-5".to_string()),
+            timeout: Some(120),
+            system: Some("CONFIG".to_string()),
+            ..Settings::default()
+        };
+
+        assert_eq!(
+            template.apply(settings),
+            Settings {
+                model: Some("gpt-5-mini".to_string()),
+                temperature: Some(0.2),
+                timeout: Some(120),
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn load_script_skips_shebang() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("haiku")?;
+        file.write_str("#!/usr/bin/env cogni\n---\nmodel = \"gpt-5-mini\"\n---\n>>> system\nReply in a haiku\n")?;
+
+        let script = load_script(file.path().to_str().unwrap())?.expect("should be script");
+        assert_eq!(script.settings.model.as_deref(), Some("gpt-5-mini"));
+        assert_eq!(script.messages, vec![Message::system("Reply in a haiku")]);
+        Ok(())
+    }
+
+    #[test]
+    fn load_script_not_script() -> Result<(), Box<dyn std::error::Error>> {
+        let file = assert_fs::NamedTempFile::new("input.txt")?;
+        file.write_str("Hello")?;
+
+        assert_eq!(load_script(file.path().to_str().unwrap())?, None);
+        assert_eq!(load_script("does_not_exist")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn render_template() {
+        let template = Template::parse(TEMPLATE, Path::new("")).expect("should parse");
+        let messages = template
+            .render(&vars(&[
+                ("style", "conventional"),
+                (INPUT_VAR, "diff with {{style}}"),
+            ]))
+            .expect("should render");
+
+        assert_eq!(
+            messages,
+            vec![
+                Message::system("Write a commit message in conventional style"),
+                Message::user("diff with {{style}}"),
+                Message::assistant("Understood, conventional."),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_template_missing_variables() {
+        let template = Template::parse(TEMPLATE, Path::new("")).expect("should parse");
+        let err = template.render(&vars(&[])).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::MissingTemplateVariables(missing) if missing == &["input", "style"]),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn placeholders_ignore_literal_braces() {
+        let text = "{{}} and {{ not a name }} and {{\nstyle}} but {{{style}}}";
+        assert_eq!(placeholders(text).collect::<Vec<_>>(), vec!["style"]);
+        assert_eq!(
+            replace_placeholders(text, |name| name.to_uppercase()),
+            "{{}} and {{ not a name }} and {{\nstyle}} but {STYLE}"
+        );
+    }
+
+    #[test]
+    fn render_template_literal_braces() {
+        let template = Template::parse(
+            ">>> user\nFormat as {\"a\": {{}}} for {{ name }}, not {{",
+            Path::new(""),
+        )
+        .expect("should parse");
+        assert_eq!(template.variables(), BTreeSet::from(["name"]));
+        let messages = template
+            .render(&vars(&[("name", "me")]))
+            .expect("should render");
+        assert_eq!(
+            messages[0],
+            Message::user("Format as {\"a\": {{}}} for me, not {{")
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Function tool definitions
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::Error;
+use crate::openai::Tool;
+use crate::template;
+
+/// Tool that agent mode executes as a local command
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CommandTool {
+    #[serde(flatten)]
+    pub tool: Tool,
+    /// Shell command, with `{{param}}` placeholders for arguments
+    pub command: String,
+}
+
+/// Load JSON array of tool definitions at `path`
+pub fn load(path: &str) -> Result<Vec<Tool>, Error> {
+    let content = std::fs::read_to_string(path)?;
+    parse(&content)
+}
+
+/// Load JSON array of tool definitions with commands at `path`
+pub fn load_commands(path: &str) -> Result<Vec<CommandTool>, Error> {
+    let content = std::fs::read_to_string(path)?;
+    parse_commands(&content)
+}
+
+/// Parse JSON array of tool definitions, e.g. `[{"name": ..., "parameters": {...}}]`
+pub fn parse(content: &str) -> Result<Vec<Tool>, Error> {
+    parse_as(content, |tool: &Tool| tool)
+}
+
+/// Parse JSON array of tool definitions with commands, e.g. `[{"name": ..., "command": ...}]`
+pub fn parse_commands(content: &str) -> Result<Vec<CommandTool>, Error> {
+    parse_as(content, |tool: &CommandTool| &tool.tool)
+}
+
+fn parse_as<T: DeserializeOwned>(
+    content: &str,
+    definition: impl Fn(&T) -> &Tool,
+) -> Result<Vec<T>, Error> {
+    let mut names = HashSet::new();
+
+    serde_json::from_str::<Vec<Value>>(content)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, record)| {
+            let invalid = |reason: String| Error::InvalidTool { index, reason };
+
+            let record = serde_json::from_value::<T>(record).map_err(|e| invalid(e.to_string()))?;
+            let tool = definition(&record);
+            jsonschema::validator_for(&tool.parameters)
+                .map_err(|e| invalid(format!("invalid parameters schema - {e}")))?;
+            if !names.insert(tool.name.clone()) {
+                return Err(invalid(format!("duplicate tool name \"{}\"", tool.name)));
+            }
+
+            Ok(record)
+        })
+        .collect()
+}
+
+impl CommandTool {
+    /// Render command for JSON object `arguments`. Placeholders are replaced by shell-quoted
+    /// argument values, or an empty string for missing arguments.
+    pub fn render(&self, arguments: &str) -> Result<String, Error> {
+        let arguments = match serde_json::from_str::<Value>(arguments)? {
+            Value::Object(args) => args,
+            Value::Null => Default::default(),
+            other => {
+                return Err(Error::UnexpectedResponse(format!(
+                    "tool arguments are not a JSON object: {other}"
+                )));
+            }
+        };
+
+        let command = template::replace_placeholders(&self.command, |name| {
+            let value = match arguments.get(name) {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            };
+            shell_quote(&value)
+        });
+
+        Ok(command)
+    }
+}
+
+/// Quote `value` as a single shell word
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_tools() {
+        let tools = parse(
+            r#"[
+                {"type": "function", "name": "get_weather", "description": "Gets weather", "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}},
+                {"name": "get_time"}
+            ]"#,
+        )
+        .expect("should succeed");
+
+        assert_eq!(
+            tools,
+            vec![
+                Tool {
+                    name: "get_weather".to_string(),
+                    description: Some("Gets weather".to_string()),
+                    parameters: json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+                    strict: None,
+                },
+                Tool {
+                    name: "get_time".to_string(),
+                    description: None,
+                    parameters: json!({"type": "object", "properties": {}}),
+                    strict: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_tools_missing_name() {
+        let err = parse(r#"[{"name": "a"}, {"description": "no name"}]"#).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidTool { index: 1, reason } if reason.contains("name")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_tools_duplicate_name() {
+        let err = parse(r#"[{"name": "a"}, {"name": "a"}]"#).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidTool { index: 1, reason } if reason.contains("duplicate")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn parse_command_tools() {
+        let tools = parse_commands(r#"[{"name": "ls", "command": "ls {{path}}"}]"#)
+            .expect("should succeed");
+        assert_eq!(tools[0].tool.name, "ls");
+        assert_eq!(tools[0].command, "ls {{path}}");
+    }
+
+    #[test]
+    fn parse_command_tools_missing_command() {
+        let err = parse_commands(r#"[{"name": "ls"}]"#).expect_err("should fail");
+        assert!(
+            matches!(&err, Error::InvalidTool { index: 0, reason } if reason.contains("command")),
+            "unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn render_command() {
+        let tool = parse_commands(
+            r#"[{"name": "grep", "command": "grep -n {{ pattern }} {{path}} | head -n {{limit}} {{missing}}"}]"#,
+        )
+        .unwrap()
+        .remove(0);
+
+        let command = tool
+            .render(r#"{"pattern": "it's $HOME", "path": "src/main.rs", "limit": 5}"#)
+            .expect("should succeed");
+
+        assert_eq!(
+            command,
+            r"grep -n 'it'\''s $HOME' 'src/main.rs' | head -n '5' ''"
+        );
+    }
+
+    #[test]
+    fn render_command_invalid_arguments() {
+        let tool = parse_commands(r#"[{"name": "ls", "command": "ls"}]"#)
+            .unwrap()
+            .remove(0);
+        assert!(tool.render("[1, 2]").is_err());
+        assert!(tool.render("not json").is_err());
+    }
+
+    #[test]
+    fn parse_tools_invalid_parameters() {
+        let err = parse(r#"[{"name": "a", "parameters": {"type": 1}}]"#).expect_err("should fail");
+        assert!(
+            matches!(err, Error::InvalidTool { index: 0, .. }),
+            "unexpected error: {err:?}"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Integration tests for agent subcommand
+
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use serde_json::json;
+
+/// Mock reply calling `echo` tool with `text`
+fn mock_tool_call(server: &mut mockito::Server, text: &str) -> mockito::Mock {
+    server
+        .mock("POST", "/v1/responses")
+        .with_body(
+            json!({
+                "created": 1688413145,
+                "model": "gpt-5.5",
+                "output": [{
+                    "type": "function_call",
+                    "call_id": "call_1",
+                    "name": "echo",
+                    "arguments": json!({ "text": text }).to_string(),
+                }],
+                "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+            })
+            .to_string(),
+        )
+        .expect(1)
+        .create()
+}
+
+fn config() -> assert_fs::NamedTempFile {
+    let config = assert_fs::NamedTempFile::new("agent.json").unwrap();
+    config
+        .write_str(
+            r#"[{
+                "name": "echo",
+                "description": "Echoes text",
+                "parameters": {"type": "object", "properties": {"text": {"type": "string"}}},
+                "command": "echo {{text}} | tr a-z A-Z"
+            }]"#,
+        )
+        .unwrap();
+    config
+}
+
+#[test]
+fn agent_runs_tools_until_reply() {
+    let mut server = mockito::Server::new();
+    let config = config();
+
+    let call = mock_tool_call(&mut server, "hello; rm -rf /");
+    let reply = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "tools": [{"type": "function", "name": "echo"}],
+            "input": [{
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "Shout hello" }],
+            }, {
+                "type": "function_call",
+                "call_id": "call_1",
+                "name": "echo",
+            }, {
+                "type": "function_call_output",
+                "call_id": "call_1",
+                "output": "HELLO; RM -RF /\n",
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "HELLO" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .expect(1)
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args([
+            "agent",
+            "--yes",
+            "--config",
+            config.path().to_str().unwrap(),
+        ])
+        .args(["-u", "Shout hello"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    call.assert();
+    reply.assert();
+
+    cmd.success()
+        .stdout("HELLO\n")
+        .stderr(predicate::str::contains(
+            "+ echo 'hello; rm -rf /' | tr a-z A-Z",
+        ));
+}
+
+#[test]
+fn agent_max_steps() {
+    let mut server = mockito::Server::new();
+    let config = config();
+
+    let call = mock_tool_call(&mut server, "again").expect(2);
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["agent", "--yes", "--max-steps", "2"])
+        .args(["--config", config.path().to_str().unwrap(), "-u", "Loop"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    call.assert();
+
+    cmd.failure()
+        .stderr(predicate::str::contains("did not finish within 2 steps"));
+}
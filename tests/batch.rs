@@ -0,0 +1,192 @@
+//! Integration tests for batch subcommand
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::json;
+
+/// Mock reply of `reply` for a request whose only user message is `input`
+fn mock_reply(server: &mut mockito::Server, input: &str, reply: &str) -> mockito::Mock {
+    server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{
+                "role": "system",
+                "content": [{ "type": "input_text", "text": "Classify" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": input }],
+            }],
+        })))
+        .with_body(
+            json!({
+                "created": 1688413145,
+                "model": "gpt-5.5",
+                "output": [{
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{ "type": "output_text", "text": reply }]
+                }],
+                "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+            })
+            .to_string(),
+        )
+        .create()
+}
+
+#[test]
+fn batch_lines_in_order() {
+    let mut server = mockito::Server::new();
+
+    let mocks = [
+        mock_reply(&mut server, "error: disk full", "ERROR"),
+        mock_reply(&mut server, "info: started", "INFO"),
+        mock_reply(&mut server, "warn: slow query", "WARN"),
+    ];
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["batch", "-s", "Classify", "-j", "2"])
+        .write_stdin("error: disk full\ninfo: started\n\nwarn: slow query\n")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    for mock in mocks {
+        mock.assert();
+    }
+
+    cmd.success().stdout("ERROR\nINFO\nWARN\n");
+}
+
+#[test]
+fn batch_ndjson_records_with_ids() {
+    let mut server = mockito::Server::new();
+
+    let mocks = [
+        mock_reply(&mut server, "first", "ONE"),
+        mock_reply(&mut server, "second", "TWO"),
+    ];
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args([
+            "batch",
+            "-s",
+            "Classify",
+            "--records",
+            "ndjson",
+            "-o",
+            "ndjson",
+        ])
+        .write_stdin(concat!(
+            r#"{"id": "a", "input": "first"}"#,
+            "\n",
+            r#"{"id": "b", "messages": [{"role": "user", "content": "second"}]}"#,
+            "\n",
+        ))
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    for mock in mocks {
+        mock.assert();
+    }
+
+    cmd.success()
+        .stdout(predicate::str::contains(r#""id":"a","text":"ONE""#))
+        .stdout(predicate::str::contains(r#""id":"b","text":"TWO""#));
+}
+
+#[test]
+fn batch_record_failure_does_not_abort() {
+    let mut server = mockito::Server::new();
+
+    let ok = mock_reply(&mut server, "good", "FINE");
+    let bad = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{}, {
+                "content": [{ "type": "input_text", "text": "bad" }],
+            }],
+        })))
+        .with_status(400)
+        .with_body(
+            r#"{
+               "error": {
+                 "message": "Invalid prompt",
+                 "type": "invalid_request_error",
+                 "param": null,
+                 "code": null
+               }
+             }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["batch", "-s", "Classify", "-o", "ndjson"])
+        .write_stdin("bad\ngood\n")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    ok.assert();
+    bad.assert();
+
+    cmd.failure()
+        .stdout(predicate::str::contains(
+            r#"{"type":"error","id":"0","error":"failed to fetch request: openai api returned error - Invalid prompt"}"#,
+        ))
+        .stdout(predicate::str::contains(r#""id":"1","text":"FINE""#))
+        .stderr(predicate::str::contains("record 0 failed"))
+        .stderr(predicate::str::contains("1 of 2 records failed"));
+}
+
+#[test]
+fn batch_incomplete_record_printed() {
+    let mut server = mockito::Server::new();
+
+    let ok = mock_reply(&mut server, "short", "FINE");
+    let truncated = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{}, {
+                "content": [{ "type": "input_text", "text": "long" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "status": "incomplete",
+             "incomplete_details": { "reason": "max_output_tokens" },
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "Once upon a" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 3, "total_tokens": 11 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["batch", "-s", "Classify"])
+        .write_stdin("long\nshort\n")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    ok.assert();
+    truncated.assert();
+
+    cmd.code(3)
+        .stdout("Once upon a\nFINE\n")
+        .stderr(predicate::str::contains(
+            "record 0 incomplete - response is incomplete - output token limit reached",
+        ))
+        .stderr(predicate::str::contains(
+            "response is incomplete - 1 of 2 records cut short",
+        ));
+}
@@ -0,0 +1,135 @@
+//! Integration tests for response cache
+
+mod common;
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::json;
+
+fn cogni(server: &mockito::Server, cache: &assert_fs::TempDir) -> Command {
+    let mut cmd = common::cogni(cache);
+    cmd.env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE");
+    cmd
+}
+
+fn mock_response(server: &mut mockito::Server, text: &str) -> mockito::Mock {
+    common::mock_response(server).match_body(mockito::Matcher::PartialJson(json!({
+        "input": [{
+            "role": "user",
+            "content": [{ "type": "input_text", "text": text }],
+        }],
+    })))
+}
+
+#[test]
+fn chat_cache_hit() {
+    let mut server = mockito::Server::new();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let mock = mock_response(&mut server, "Hello").expect(1).create();
+
+    cogni(&server, &cache)
+        .args(["--cache", "-u", "Hello", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"cached\"").not());
+
+    // Repeated request is served from cache
+    cogni(&server, &cache)
+        .args(["--cache", "-u", "Hello", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ASSISTANT REPLY"))
+        .stdout(predicate::str::contains("\"cached\":true"));
+
+    cogni(&server, &cache)
+        .args(["--cache", "--stream", "-u", "Hello"])
+        .assert()
+        .success()
+        .stdout("ASSISTANT REPLY\n");
+
+    mock.assert();
+}
+
+#[test]
+fn chat_cache_disabled() {
+    let mut server = mockito::Server::new();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let mock = mock_response(&mut server, "Hello").expect(3).create();
+
+    // Not cached unless requested
+    cogni(&server, &cache)
+        .args(["-u", "Hello"])
+        .assert()
+        .success();
+    cogni(&server, &cache)
+        .args(["-u", "Hello"])
+        .assert()
+        .success();
+
+    // `--no-cache` after `--cache` bypasses cache
+    cogni(&server, &cache)
+        .args(["--cache", "--no-cache", "-u", "Hello"])
+        .assert()
+        .success();
+
+    mock.assert();
+}
+
+#[test]
+fn chat_cache_different_requests() {
+    let mut server = mockito::Server::new();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let hello = mock_response(&mut server, "Hello").expect(1).create();
+    let bye = mock_response(&mut server, "Bye").expect(1).create();
+
+    cogni(&server, &cache)
+        .args(["--cache", "-u", "Hello"])
+        .assert()
+        .success();
+    cogni(&server, &cache)
+        .args(["--cache", "-u", "Bye"])
+        .assert()
+        .success();
+
+    hello.assert();
+    bye.assert();
+}
+
+#[test]
+fn cache_stats_and_clear() {
+    let mut server = mockito::Server::new();
+    let cache = assert_fs::TempDir::new().unwrap();
+    let mock = mock_response(&mut server, "Hello").expect(2).create();
+
+    cogni(&server, &cache)
+        .args(["--cache", "-u", "Hello"])
+        .assert()
+        .success();
+
+    cogni(&server, &cache)
+        .args(["cache", "stats"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("entries = 1"));
+
+    cogni(&server, &cache)
+        .args(["cache", "clear"])
+        .assert()
+        .success()
+        .stdout("removed 1 cached responses\n");
+
+    cogni(&server, &cache)
+        .args(["cache", "stats"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("entries = 0"));
+
+    // Cleared response is fetched again
+    cogni(&server, &cache)
+        .args(["--cache", "-u", "Hello"])
+        .assert()
+        .success();
+
+    mock.assert();
+}
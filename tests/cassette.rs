@@ -0,0 +1,149 @@
+//! Integration tests for recording and replaying API interactions
+
+mod common;
+
+use assert_cmd::Command;
+use common::mock_response;
+use predicates::prelude::*;
+use serde_json::json;
+
+/// Endpoint that nothing listens on, so replayed requests fail if they reach the network
+const UNREACHABLE: &str = "http://127.0.0.1:9";
+
+#[test]
+fn record_then_replay() {
+    let mut server = mockito::Server::new();
+    let mock = mock_response(&mut server).expect(1).create();
+    let cassette = assert_fs::NamedTempFile::new("cassette.ndjson").unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello"])
+        .env("COGNI_RECORD", cassette.path())
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert()
+        .success()
+        .stdout("ASSISTANT REPLY\n");
+
+    mock.assert();
+
+    let recording = std::fs::read_to_string(cassette.path()).unwrap();
+    let interaction: serde_json::Value = serde_json::from_str(recording.trim()).unwrap();
+    assert_eq!(interaction["status"], 200);
+    assert_eq!(
+        interaction["request"]["input"],
+        json!([{ "role": "user", "content": [{ "type": "input_text", "text": "Hello" }] }])
+    );
+    assert!(
+        !recording.contains("ABCDE"),
+        "API key should not be recorded"
+    );
+
+    // Replayed without network or API key
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello"])
+        .env("COGNI_REPLAY", cassette.path())
+        .env("OPENAI_API_ENDPOINT", UNREACHABLE)
+        .env_remove("OPENAI_API_KEY")
+        .assert()
+        .success()
+        .stdout("ASSISTANT REPLY\n");
+}
+
+#[test]
+fn record_then_replay_stream() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({ "stream": true })))
+        .with_header("content-type", "text/event-stream")
+        .with_body(concat!(
+            "data: {\"type\":\"response.output_text.delta\",\"delta\":\"Hello\"}\n\n",
+            "data: {\"type\":\"response.output_text.delta\",\"delta\":\" world\"}\n\n",
+            "data: {\"type\":\"response.completed\",\"response\":{\"created_at\":1688413145,\"model\":\"gpt-5.5\",\"output\":[{\"type\":\"message\",\"role\":\"assistant\",\"content\":[{\"type\":\"output_text\",\"text\":\"Hello world\"}]}],\"usage\":{\"input_tokens\":1,\"output_tokens\":2,\"total_tokens\":3}}}\n\n",
+        ))
+        .expect(1)
+        .create();
+    let cassette = assert_fs::NamedTempFile::new("cassette.ndjson").unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--stream", "-u", "Hello"])
+        .env("COGNI_RECORD", cassette.path())
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert()
+        .success()
+        .stdout("Hello world\n");
+
+    mock.assert();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--stream", "-u", "Hello"])
+        .env("COGNI_REPLAY", cassette.path())
+        .env("OPENAI_API_ENDPOINT", UNREACHABLE)
+        .env_remove("OPENAI_API_KEY")
+        .assert()
+        .success()
+        .stdout("Hello world\n");
+}
+
+#[test]
+fn replay_recorded_error() {
+    let cassette = assert_fs::NamedTempFile::new("cassette.ndjson").unwrap();
+    let interaction = json!({
+        "request": {
+            "model": "gpt-5.5",
+            "input": [{ "role": "user", "content": [{ "type": "input_text", "text": "Hello" }] }],
+        },
+        "status": 429,
+        "body": r#"{"error": {"message": "Rate limit reached", "type": "requests", "param": null, "code": "rate_limit_exceeded"}}"#,
+    });
+    std::fs::write(cassette.path(), format!("{interaction}\n")).unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello"])
+        .env("COGNI_REPLAY", cassette.path())
+        .env("OPENAI_API_ENDPOINT", UNREACHABLE)
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("Rate limit reached"));
+}
+
+#[test]
+fn replay_without_recording() {
+    let cassette = assert_fs::NamedTempFile::new("cassette.ndjson").unwrap();
+    std::fs::write(cassette.path(), "").unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello"])
+        .env("COGNI_REPLAY", cassette.path())
+        .env("OPENAI_API_ENDPOINT", UNREACHABLE)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "no recording in {} matches request",
+            cassette.path().display()
+        )));
+}
+
+#[test]
+fn replay_unsupported_provider() {
+    let cassette = assert_fs::NamedTempFile::new("cassette.ndjson").unwrap();
+    std::fs::write(cassette.path(), "").unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-m", "ollama:llama3", "-u", "Hello"])
+        .env("COGNI_REPLAY", cassette.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "recording and replaying is not supported by ollama provider",
+        ));
+}
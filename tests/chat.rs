@@ -326,7 +326,7 @@ fn chat_api_error() {
 
     mock.assert();
 
-    cmd.failure().stderr(predicate::str::contains(
+    cmd.code(7).stderr(predicate::str::contains(
         "1000 is greater than the maximum of 2",
     ));
 }
@@ -388,3 +388,1026 @@ fn chat_user_message_from_file() {
     cmd.success()
         .stdout(predicate::str::contains("ASSISTANT REPLY"));
 }
+
+/// Test streamed responses are written as deltas arrive
+#[test]
+fn chat_stream() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "model": "gpt-5.5",
+            "stream": true,
+        })))
+        .with_header("content-type", "text/event-stream")
+        .with_body(
+            r#"event: response.created
+data: {"type":"response.created","response":{"id":"resp_XXXXX"}}
+
+event: response.output_text.delta
+data: {"type":"response.output_text.delta","delta":"ASSISTANT"}
+
+event: response.output_text.delta
+data: {"type":"response.output_text.delta","delta":" REPLY"}
+
+event: response.completed
+data: {"type":"response.completed","response":{"id":"resp_XXXXX","created_at":1688413145,"model":"gpt-5.5","output":[{"type":"message","role":"assistant","content":[{"type":"output_text","text":"ASSISTANT REPLY"}]}],"usage":{"input_tokens":8,"output_tokens":9,"total_tokens":17}}}
+
+"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello", "--stream"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("ASSISTANT REPLY\n");
+}
+
+/// Test streamed responses still provide usage for JSON output
+#[test]
+fn chat_stream_json() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({ "stream": true })))
+        .with_header("content-type", "text/event-stream")
+        .with_body(
+            r#"data: {"type":"response.output_text.delta","delta":"ASSISTANT REPLY"}
+
+data: {"type":"response.completed","response":{"created_at":1688413145,"model":"gpt-5.5","output":[{"type":"message","role":"assistant","content":[{"type":"output_text","text":"ASSISTANT REPLY"}]}],"usage":{"input_tokens":8,"output_tokens":9,"total_tokens":17}}}
+
+"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello", "--stream", "--json"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success()
+        .stdout(predicate::str::contains("\"total_tokens\":17"));
+}
+
+/// Test failed streams are reported as errors
+#[test]
+fn chat_stream_failed() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_header("content-type", "text/event-stream")
+        .with_body(
+            r#"data: {"type":"response.failed","response":{"error":{"code":"server_error","message":"The model failed"}}}
+
+"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello", "--stream"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.failure()
+        .stderr(predicate::str::contains("The model failed"));
+}
+
+/// Test messages from transcript file
+#[test]
+fn chat_transcript_from_file() {
+    let mut server = mockito::Server::new();
+
+    let infile = assert_fs::NamedTempFile::new("convo.txt").unwrap();
+    infile
+        .write_str(
+            ">>> system\nSYSTEM\n\n>>> user\nUSER_1\n\n>>> assistant\nASSI_1\n\n>>> user\nUSER_2\n",
+        )
+        .unwrap();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{
+                "role": "system",
+                "content": [{ "type": "input_text", "text": "SYSTEM" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "USER_1" }],
+            }, {
+                "role": "assistant",
+                "content": [{ "type": "input_text", "text": "ASSI_1" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "USER_2" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{
+                     "type": "output_text",
+                     "text": "ASSISTANT REPLY"
+                 }]
+             }],
+             "usage": {
+                 "input_tokens": 8,
+                 "output_tokens": 9,
+                 "total_tokens": 17
+             }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args([
+            "--input-format",
+            "transcript",
+            infile.path().to_str().unwrap(),
+        ])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success()
+        .stdout(predicate::str::contains("ASSISTANT REPLY"));
+}
+
+/// Test malformed transcripts report line numbers
+#[test]
+fn chat_transcript_invalid_marker() {
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--input-format", "transcript"])
+        .write_stdin(">>> user\nHello\n>>> narrator\nHi")
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid transcript at line 3"));
+}
+
+/// Test messages piped from JSON producers
+#[test]
+fn chat_json_messages_from_stdin() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{
+                "role": "system",
+                "content": [{ "type": "input_text", "text": "SYSTEM" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "USER" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{
+                     "type": "output_text",
+                     "text": "ASSISTANT REPLY"
+                 }]
+             }],
+             "usage": {
+                 "input_tokens": 8,
+                 "output_tokens": 9,
+                 "total_tokens": 17
+             }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--input-format", "ndjson"])
+        .write_stdin(
+            "{\"role\": \"system\", \"content\": \"SYSTEM\"}\n{\"role\": \"user\", \"content\": \"USER\"}\n",
+        )
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success()
+        .stdout(predicate::str::contains("ASSISTANT REPLY"));
+}
+
+/// Test invalid JSON messages report the offending record
+#[test]
+fn chat_json_messages_invalid_record() {
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--input-format", "json"])
+        .write_stdin(r#"[{"role": "user", "content": "Hi"}, {"role": "robot", "content": "BEEP"}]"#)
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid message at record 1"));
+}
+
+/// Test transcript output can be fed back as input
+#[test]
+fn chat_transcript_round_trip() {
+    let mut server = mockito::Server::new();
+
+    let first = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "USER_1" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "ASSI_1" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let convo = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-o", "transcript"])
+        .write_stdin("USER_1")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .output()
+        .unwrap();
+
+    first.assert();
+    assert!(convo.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&convo.stdout),
+        ">>> user\nUSER_1\n\n>>> assistant\nASSI_1\n"
+    );
+
+    let second = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "USER_1" }],
+            }, {
+                "role": "assistant",
+                "content": [{ "type": "input_text", "text": "ASSI_1" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "USER_2" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "ASSI_2" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let mut next_input = convo.stdout.clone();
+    next_input.extend_from_slice(b"\n>>> user\nUSER_2\n");
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-i", "transcript", "-o", "transcript"])
+        .write_stdin(next_input)
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    second.assert();
+
+    cmd.success().stdout(
+        ">>> user\nUSER_1\n\n>>> assistant\nASSI_1\n\n>>> user\nUSER_2\n\n>>> assistant\nASSI_2\n",
+    );
+}
+
+/// Test NDJSON output emits one event per line when streaming
+#[test]
+fn chat_stream_ndjson() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({ "stream": true })))
+        .with_header("content-type", "text/event-stream")
+        .with_body(
+            r#"data: {"type":"response.output_text.delta","delta":"ASSISTANT"}
+
+data: {"type":"response.output_text.delta","delta":" REPLY"}
+
+data: {"type":"response.completed","response":{"created_at":1688413145,"model":"gpt-5.5","output":[{"type":"message","role":"assistant","content":[{"type":"output_text","text":"ASSISTANT REPLY"}]}],"usage":{"input_tokens":8,"output_tokens":9,"total_tokens":17}}}
+
+"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello", "--stream", "-o", "ndjson", "--id", "42"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout(concat!(
+        r#"{"type":"delta","id":"42","delta":"ASSISTANT"}"#,
+        "\n",
+        r#"{"type":"delta","id":"42","delta":" REPLY"}"#,
+        "\n",
+        r#"{"type":"response","id":"42","text":"ASSISTANT REPLY","model":"gpt-5.5","usage":{"input_tokens":8,"output_tokens":9,"total_tokens":17},"finish_reason":"stop"}"#,
+        "\n",
+    ));
+}
+
+/// Test rate limited and server error responses are retried
+#[test]
+fn chat_retries_transient_errors() {
+    let mut server = mockito::Server::new();
+
+    let rate_limited = server
+        .mock("POST", "/v1/responses")
+        .with_status(429)
+        .with_header("retry-after", "0")
+        .with_body(
+            r#"{
+               "error": {
+                 "message": "Rate limit reached",
+                 "type": "requests",
+                 "param": null,
+                 "code": "rate_limit_exceeded"
+               }
+             }"#,
+        )
+        .expect(1)
+        .create();
+
+    let unavailable = server
+        .mock("POST", "/v1/responses")
+        .with_status(503)
+        .with_header("retry-after-ms", "10")
+        .with_body(r#"{"error": {"message": "Overloaded", "type": "server_error", "param": null, "code": null}}"#)
+        .expect(1)
+        .create();
+
+    let ok = server
+        .mock("POST", "/v1/responses")
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "ASSISTANT REPLY" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .expect(1)
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello", "--retries", "2"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    rate_limited.assert();
+    unavailable.assert();
+    ok.assert();
+
+    cmd.success()
+        .stdout(predicate::str::contains("ASSISTANT REPLY"))
+        .stderr(predicate::str::contains("retrying"));
+}
+
+/// Test retries give up with number of attempts made
+#[test]
+fn chat_retries_exhausted() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_status(503)
+        .with_header("retry-after", "0")
+        .with_body(r#"{"error": {"message": "Overloaded", "type": "server_error", "param": null, "code": null}}"#)
+        .expect(3)
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello", "--retries", "2"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.failure()
+        .stderr(predicate::str::contains("gave up after 3 attempts"))
+        .stderr(predicate::str::contains("Overloaded"));
+}
+
+/// Test retries can be disabled
+#[test]
+fn chat_retries_disabled() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_status(503)
+        .with_body(r#"{"error": {"message": "Overloaded", "type": "server_error", "param": null, "code": null}}"#)
+        .expect(1)
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello", "--retries", "0"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.failure()
+        .stderr(predicate::str::contains("Overloaded"))
+        .stderr(predicate::str::contains("gave up").not());
+}
+
+/// Test errors are printed as JSON with exit status per error kind
+#[test]
+fn chat_error_format_json() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_status(429)
+        .with_body(
+            r#"{
+               "error": {
+                 "message": "Rate limit reached",
+                 "type": "requests",
+                 "param": null,
+                 "code": "rate_limit_exceeded"
+               }
+             }"#,
+        )
+        .expect(1)
+        .create();
+
+    let output = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello", "--retries", "0", "--error-format", "json"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .output()
+        .unwrap();
+
+    mock.assert();
+
+    assert_eq!(output.status.code(), Some(5));
+    let report: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(
+        report,
+        json!({
+            "kind": "rate_limit",
+            "message": "failed to fetch request: rate limited - Rate limit reached",
+            "status": 429,
+            "code": "rate_limit_exceeded",
+            "param": null,
+        })
+    );
+}
+
+/// Test missing API key has its own exit status
+#[test]
+fn chat_no_api_key() {
+    let config = assert_fs::TempDir::new().unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Hello"])
+        .env_remove("OPENAI_API_KEY")
+        .env("COGNI_CONFIG", config.path().join("config.toml"))
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("no API key provided"));
+}
+
+/// Test structured output requests schema and validates output
+#[test]
+fn chat_schema() {
+    let mut server = mockito::Server::new();
+
+    let schema = assert_fs::NamedTempFile::new("event.json").unwrap();
+    schema
+        .write_str(r#"{"type": "object", "properties": {"level": {"type": "string"}}, "required": ["level"], "additionalProperties": false}"#)
+        .unwrap();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "text": {
+                "format": {
+                    "type": "json_schema",
+                    "name": "event",
+                    "strict": true,
+                    "schema": {"type": "object"},
+                }
+            }
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "{\"level\": \"error\"}" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--schema", schema.path().to_str().unwrap()])
+        .write_stdin("ERROR disk full")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("{\"level\": \"error\"}\n");
+}
+
+/// Test output not matching schema fails with validation path
+#[test]
+fn chat_schema_mismatch() {
+    let mut server = mockito::Server::new();
+
+    let schema = assert_fs::NamedTempFile::new("event.json").unwrap();
+    schema
+        .write_str(r#"{"type": "object", "properties": {"level": {"type": "string"}}, "required": ["level"]}"#)
+        .unwrap();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "{\"level\": 3}" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--schema", schema.path().to_str().unwrap()])
+        .write_stdin("ERROR disk full")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.failure().stdout("").stderr(predicate::str::contains(
+        "output does not match schema at /level",
+    ));
+}
+
+#[test]
+fn chat_tool_calls() {
+    let mut server = mockito::Server::new();
+
+    let tools = assert_fs::NamedTempFile::new("tools.json").unwrap();
+    tools
+        .write_str(r#"[{"name": "get_weather", "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}}]"#)
+        .unwrap();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "tools": [{"type": "function", "name": "get_weather"}]
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "function_call",
+                 "call_id": "call_1",
+                 "name": "get_weather",
+                 "arguments": "{\"city\": \"Tokyo\"}"
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--tools", tools.path().to_str().unwrap()])
+        .write_stdin("Weather in Tokyo?")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success()
+        .stdout("call_1\tget_weather\t{\"city\":\"Tokyo\"}\n");
+}
+
+#[test]
+fn chat_tool_outputs_from_transcript() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{
+                "role": "user",
+                "content": [{"type": "input_text", "text": "Weather in Tokyo?"}]
+            }, {
+                "type": "function_call",
+                "call_id": "call_1",
+                "name": "get_weather",
+                "arguments": "{\"city\": \"Tokyo\"}"
+            }, {
+                "type": "function_call_output",
+                "call_id": "call_1",
+                "output": "Sunny"
+            }]
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "It is sunny in Tokyo" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-i", "transcript"])
+        .write_stdin(
+            ">>> user\nWeather in Tokyo?\n\n>>> tool_call call_1 get_weather\n{\"city\": \"Tokyo\"}\n\n>>> tool call_1\nSunny\n",
+        )
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("It is sunny in Tokyo\n");
+}
+
+#[test]
+fn chat_continue_previous_response() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    let id_file = dir.child("last_id");
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "previous_response_id": "resp_1",
+            "store": true,
+            "input": [{
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "And in French?" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "id": "resp_2",
+             "created_at": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "Bonjour" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--continue", "resp_1", "--store", "--print-id", "--id-file"])
+        .arg(id_file.path())
+        .args(["-u", "And in French?"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("Bonjour\n").stderr("resp_2\n");
+    id_file.assert("resp_2\n");
+}
+
+#[test]
+fn chat_continue_unsupported_provider() {
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--continue", "resp_1", "-m", "ollama:llama3", "-u", "Hello"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--continue is not supported by ollama provider",
+        ));
+}
+
+const INCOMPLETE_RESPONSE: &str = r#"{
+     "created": 1688413145,
+     "model": "gpt-5.5",
+     "status": "incomplete",
+     "incomplete_details": { "reason": "max_output_tokens" },
+     "output": [{
+         "type": "message",
+         "role": "assistant",
+         "content": [{ "type": "output_text", "text": "Once upon a" }]
+     }],
+     "usage": { "input_tokens": 8, "output_tokens": 3, "total_tokens": 11 }
+}"#;
+
+#[test]
+fn chat_incomplete_response_printed() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "max_output_tokens": 3,
+        })))
+        .with_body(INCOMPLETE_RESPONSE)
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--max-output-tokens", "3", "-u", "Tell me a story"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.code(3)
+        .stdout("Once upon a\n")
+        .stderr(predicate::str::contains(
+            "response is incomplete - output token limit reached",
+        ));
+}
+
+#[test]
+fn chat_incomplete_response_fail() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_body(INCOMPLETE_RESPONSE)
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args([
+            "--on-incomplete",
+            "fail",
+            "--stream",
+            "-u",
+            "Tell me a story",
+        ])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.code(3).stdout("").stderr(predicate::str::contains(
+        "response is incomplete - output token limit reached",
+    ));
+}
+
+#[test]
+fn chat_image_attachment() {
+    let mut server = mockito::Server::new();
+    let image = assert_fs::NamedTempFile::new("screenshot.png").unwrap();
+    image.write_binary(b"\x89PNG").unwrap();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{
+                "role": "user",
+                "content": [
+                    { "type": "input_text", "text": "What is this?" },
+                    { "type": "input_image", "image_url": "data:image/png;base64,iVBORw==" },
+                ],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "A screenshot" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .arg("--image")
+        .arg(image.path())
+        .args(["-u", "What is this?"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("A screenshot\n");
+}
+
+#[test]
+fn chat_transcript_attachment_relative_to_file() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("pixel.png").write_binary(b"\x89PNG").unwrap();
+    dir.child("convo.txt")
+        .write_str(">>> user\nWhat is this?\n>>> image pixel.png\n")
+        .unwrap();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "input": [{
+                "role": "user",
+                "content": [
+                    { "type": "input_text", "text": "What is this?" },
+                    { "type": "input_image", "image_url": "data:image/png;base64,iVBORw==" },
+                ],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "A pixel" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-i", "transcript"])
+        .arg(dir.child("convo.txt").path())
+        .current_dir(std::env::temp_dir())
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("A pixel\n");
+}
+
+#[test]
+fn chat_stdin_local_attachment_requires_flag() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("secret.txt").write_str("hi").unwrap();
+    let input = ">>> user\nSummarize\n>>> file secret.txt\n";
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "Greeting" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .expect(1)
+        .create();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-i", "transcript"])
+        .current_dir(dir.path())
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .write_stdin(input)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "secret.txt - local files are only attached from input allowed by --allow-local-attachments",
+        ));
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-i", "transcript", "--allow-local-attachments"])
+        .current_dir(dir.path())
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("Greeting\n");
+
+    mock.assert();
+}
+
+#[test]
+fn chat_attachment_too_large() {
+    let file = assert_fs::NamedTempFile::new("large.pdf").unwrap();
+    std::fs::File::create(file.path())
+        .unwrap()
+        .set_len(20 * 1024 * 1024 + 1)
+        .unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .arg("--file")
+        .arg(file.path())
+        .args(["-u", "Summarize"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("exceeding limit"));
+}
@@ -0,0 +1,33 @@
+//! Fixtures shared by integration tests
+
+// Each test binary uses a subset of fixtures
+#![allow(dead_code)]
+
+use assert_cmd::Command;
+
+/// Response costing $0.006 for model `gpt-5.5`, at prices of $1 input and $10 output per million
+/// tokens
+pub const RESPONSE: &str = r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "ASSISTANT REPLY" }]
+             }],
+             "usage": { "input_tokens": 1000, "output_tokens": 500, "total_tokens": 1500 }
+        }"#;
+
+/// Mock replying `RESPONSE` to requests to OpenAI Responses API
+pub fn mock_response(server: &mut mockito::Server) -> mockito::Mock {
+    server.mock("POST", "/v1/responses").with_body(RESPONSE)
+}
+
+/// Command with config at `config.toml` in `dir`, and state and cache kept in `dir`
+pub fn cogni(dir: &assert_fs::TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("cogni").unwrap();
+    cmd.env("COGNI_CONFIG", dir.path().join("config.toml"))
+        .env("XDG_STATE_HOME", dir.path())
+        .env("XDG_CACHE_HOME", dir.path());
+    cmd
+}
@@ -0,0 +1,105 @@
+//! Integration tests for configuration file and profiles
+
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use serde_json::json;
+
+fn config() -> assert_fs::NamedTempFile {
+    let config = assert_fs::NamedTempFile::new("config.toml").unwrap();
+    config
+        .write_str(
+            r#"
+[defaults]
+model = "gpt-5"
+timeout = 120
+
+[profiles.summarizer]
+model = "gpt-5-mini"
+system = "Summarize"
+api_key_cmd = "echo KEY_FROM_CMD"
+"#,
+        )
+        .unwrap();
+    config
+}
+
+#[test]
+fn config_show_redacts_key() {
+    let config = config();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["config", "show", "--profile", "summarizer", "-T", "30"])
+        .env("COGNI_CONFIG", config.path())
+        .env("OPENAI_API_KEY", "sk-SECRET")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("profile = \"summarizer\"")
+                .and(predicate::str::contains("model = \"gpt-5-mini\""))
+                .and(predicate::str::contains("timeout = 30"))
+                .and(predicate::str::contains("system = \"Summarize\""))
+                .and(predicate::str::contains("api_key = \"<redacted>\""))
+                .and(predicate::str::contains("SECRET").not()),
+        );
+}
+
+#[test]
+fn config_unknown_profile() {
+    let config = config();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--profile", "missing", "-u", "Hello"])
+        .env("COGNI_CONFIG", config.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown profile \"missing\""));
+}
+
+#[test]
+fn chat_with_profile() {
+    let mut server = mockito::Server::new();
+    let config = config();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_header("authorization", "Bearer KEY_FROM_CMD")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "model": "gpt-5-mini",
+            "input": [{
+                "role": "system",
+                "content": [{ "type": "input_text", "text": "Summarize" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "Long text" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5-mini",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "Short text" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Long text"])
+        .env("COGNI_CONFIG", config.path())
+        .env("COGNI_PROFILE", "summarizer")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env_remove("OPENAI_API_KEY")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("Short text\n");
+}
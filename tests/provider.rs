@@ -0,0 +1,326 @@
+//! Integration tests for providers other than OpenAI Responses API
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::json;
+
+#[test]
+fn chat_completions_provider() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("authorization", "Bearer ABCDE")
+        .match_body(mockito::Matcher::Json(json!({
+            "model": "qwen",
+            "messages": [{"role": "user", "content": "Hello"}],
+        })))
+        .with_body(
+            r#"{
+             "id": "chatcmpl-1",
+             "object": "chat.completion",
+             "created": 1688413145,
+             "model": "qwen",
+             "choices": [{
+                 "index": 0,
+                 "message": {"role": "assistant", "content": "Hi there"},
+                 "finish_reason": "stop"
+             }],
+             "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7}
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args([
+            "--provider",
+            "chat-completions",
+            "-m",
+            "qwen",
+            "-u",
+            "Hello",
+        ])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("CHAT_COMPLETIONS_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("Hi there\n");
+}
+
+#[test]
+fn chat_completions_ignores_openai_api_key() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("authorization", mockito::Matcher::Missing)
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "qwen",
+             "choices": [{
+                 "index": 0,
+                 "message": {"role": "assistant", "content": "Hi there"},
+                 "finish_reason": "stop"
+             }],
+             "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7}
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args([
+            "--provider",
+            "chat-completions",
+            "-m",
+            "qwen",
+            "-u",
+            "Hello",
+        ])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "sk-openai-secret")
+        .env_remove("CHAT_COMPLETIONS_API_KEY")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("Hi there\n");
+}
+
+#[test]
+fn ollama_model_prefix_ignores_openai_api_key() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("authorization", mockito::Matcher::Missing)
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "model": "llama3:8b",
+            "stream": true,
+        })))
+        .with_header("content-type", "text/event-stream")
+        .with_body(concat!(
+            "data: {\"created\":1,\"model\":\"llama3:8b\",\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":\"Hel\"}}]}\n\n",
+            "data: {\"created\":1,\"model\":\"llama3:8b\",\"choices\":[{\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: [DONE]\n\n",
+        ))
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--stream", "-m", "ollama:llama3:8b", "-u", "Hi"])
+        .env("OLLAMA_HOST", server.url())
+        .env("OPENAI_API_KEY", "sk-openai-secret")
+        .env_remove("OLLAMA_API_KEY")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("Hello\n");
+}
+
+#[test]
+fn ollama_api_key() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("authorization", "Bearer OLLAMA-KEY")
+        .with_body(
+            r#"{
+                "created": 1,
+                "model": "llama3",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Hello" },
+                    "finish_reason": "stop"
+                }]
+            }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-m", "ollama:llama3", "-u", "Hi"])
+        .env("OLLAMA_HOST", server.url())
+        .env("OPENAI_API_KEY", "sk-openai-secret")
+        .env("OLLAMA_API_KEY", "OLLAMA-KEY")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("Hello\n");
+}
+
+#[test]
+fn anthropic_provider() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/messages")
+        .match_header("x-api-key", "ANTHROPIC_KEY")
+        .match_header("anthropic-version", "2023-06-01")
+        .match_body(mockito::Matcher::Json(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 4096,
+            "system": "Be brief",
+            "messages": [{"role": "user", "content": "Hello"}],
+        })))
+        .with_body(
+            r#"{
+             "id": "msg_1",
+             "type": "message",
+             "role": "assistant",
+             "model": "claude-sonnet-4-5",
+             "content": [{"type": "text", "text": "Hi"}],
+             "stop_reason": "end_turn",
+             "usage": {"input_tokens": 10, "output_tokens": 2}
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-m", "anthropic:claude-sonnet-4-5", "-o", "ndjson"])
+        .args(["-s", "Be brief", "-u", "Hello"])
+        .env("ANTHROPIC_API_ENDPOINT", server.url())
+        .env("ANTHROPIC_API_KEY", "ANTHROPIC_KEY")
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout(
+        r#"{"type":"response","text":"Hi","model":"claude-sonnet-4-5","usage":{"input_tokens":10,"output_tokens":2,"total_tokens":12},"finish_reason":"stop"}"#
+            .to_string()
+            + "\n",
+    );
+}
+
+#[test]
+fn anthropic_provider_error() {
+    let mut server = mockito::Server::new();
+
+    let mock = server
+        .mock("POST", "/v1/messages")
+        .with_status(400)
+        .with_body(
+            r#"{"type": "error", "error": {"type": "invalid_request_error", "message": "max_tokens: too large"}}"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args([
+            "--provider",
+            "anthropic",
+            "-m",
+            "claude-sonnet-4-5",
+            "-u",
+            "Hello",
+        ])
+        .env("ANTHROPIC_API_ENDPOINT", server.url())
+        .env("ANTHROPIC_API_KEY", "ANTHROPIC_KEY")
+        .assert();
+
+    mock.assert();
+
+    cmd.failure()
+        .stderr(predicate::str::contains("max_tokens: too large"));
+}
+
+#[test]
+fn mock_provider_rules() {
+    let rules = assert_fs::NamedTempFile::new("rules.toml").unwrap();
+    std::fs::write(
+        rules.path(),
+        r#"
+[[rules]]
+pattern = "(?i)weather in (\\w+)"
+reply = "It is sunny in $1"
+usage = { input_tokens = 3, output_tokens = 5 }
+"#,
+    )
+    .unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--provider", "mock", "-u", "Weather in Tokyo?"])
+        .env("COGNI_MOCK_RULES", rules.path())
+        .env_remove("OPENAI_API_KEY")
+        .assert()
+        .success()
+        .stdout("It is sunny in Tokyo\n");
+
+    // Same output formats as real providers
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-m", "mock:test", "-u", "Weather in Tokyo?", "--json"])
+        .env("COGNI_MOCK_RULES", rules.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"model\":\"test\""))
+        .stdout(predicate::str::contains("\"total_tokens\":8"));
+
+    // Via flag, and config
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args([
+            "--provider",
+            "mock",
+            "-u",
+            "Weather in Paris?",
+            "--mock-rules",
+        ])
+        .arg(rules.path())
+        .env_remove("COGNI_MOCK_RULES")
+        .assert()
+        .success()
+        .stdout("It is sunny in Paris\n");
+
+    let config = assert_fs::NamedTempFile::new("config.toml").unwrap();
+    std::fs::write(
+        config.path(),
+        format!(
+            "[defaults]\nprovider = \"mock\"\nmock_rules = {:?}\n",
+            rules.path().display().to_string()
+        ),
+    )
+    .unwrap();
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-u", "Weather in Oslo?"])
+        .env("COGNI_CONFIG", config.path())
+        .env_remove("COGNI_MOCK_RULES")
+        .assert()
+        .success()
+        .stdout("It is sunny in Oslo\n");
+}
+
+#[test]
+fn mock_provider_echo() {
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--provider", "mock", "--stream", "-u", "Hello there"])
+        .env_remove("COGNI_MOCK_RULES")
+        .assert()
+        .success()
+        .stdout("Hello there\n");
+}
+
+#[test]
+fn mock_provider_invalid_rules() {
+    let rules = assert_fs::NamedTempFile::new("rules.toml").unwrap();
+    std::fs::write(rules.path(), "[[rules]]\npattern = \"(\"\nreply = \"\"\n").unwrap();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["--provider", "mock", "-u", "Hello"])
+        .env("COGNI_MOCK_RULES", rules.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid mock rules"));
+}
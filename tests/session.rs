@@ -0,0 +1,175 @@
+//! Integration tests for sessions
+
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use serde_json::json;
+
+fn response(text: &str) -> String {
+    json!({
+        "created": 1688413145,
+        "model": "gpt-5-mini",
+        "output": [{
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "output_text", "text": text }]
+        }],
+        "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+    })
+    .to_string()
+}
+
+fn cogni(state: &assert_fs::TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("cogni").unwrap();
+    cmd.env("XDG_STATE_HOME", state.path())
+        .env("COGNI_CONFIG", state.path().join("config.toml"))
+        .env("OPENAI_API_KEY", "ABCDE");
+    cmd
+}
+
+#[test]
+fn session_continues_conversation() {
+    let mut server = mockito::Server::new();
+    let state = assert_fs::TempDir::new().unwrap();
+
+    let first = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "model": "gpt-5-mini",
+            "input": [{
+                "role": "system",
+                "content": [{ "type": "input_text", "text": "Be brief" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "My name is Ada" }],
+            }],
+        })))
+        .with_body(response("Hi Ada"))
+        .expect(1)
+        .create();
+
+    cogni(&state)
+        .args(["--session", "intro", "-m", "gpt-5-mini", "-s", "Be brief"])
+        .args(["-u", "My name is Ada"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .assert()
+        .success()
+        .stdout("Hi Ada\n");
+    first.assert();
+    first.remove();
+
+    // Model and history are restored from session
+    let second = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "model": "gpt-5-mini",
+            "input": [{
+                "role": "system",
+                "content": [{ "type": "input_text", "text": "Be brief" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "My name is Ada" }],
+            }, {
+                "role": "assistant",
+                "content": [{ "type": "input_text", "text": "Hi Ada" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "What is my name?" }],
+            }],
+        })))
+        .with_body(response("Ada"))
+        .expect(1)
+        .create();
+
+    cogni(&state)
+        .args(["--session", "intro"])
+        .write_stdin("What is my name?")
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .assert()
+        .success()
+        .stdout("Ada\n");
+    second.assert();
+
+    cogni(&state)
+        .args(["session", "show", "intro"])
+        .assert()
+        .success()
+        .stdout(
+            ">>> system\nBe brief\n\n>>> user\nMy name is Ada\n\n>>> assistant\nHi Ada\n\n\
+             >>> user\nWhat is my name?\n\n>>> assistant\nAda\n",
+        );
+}
+
+#[test]
+fn session_not_saved_on_failure() {
+    let mut server = mockito::Server::new();
+    let state = assert_fs::TempDir::new().unwrap();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_status(400)
+        .with_body(r#"{"error": {"message": "bad request", "type": "invalid_request_error"}}"#)
+        .create();
+
+    cogni(&state)
+        .args(["--session", "broken", "-u", "Hello", "--retries", "0"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .assert()
+        .failure();
+    mock.assert();
+
+    cogni(&state)
+        .args(["session", "list"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn session_list_fork_rm() {
+    let state = assert_fs::TempDir::new().unwrap();
+    state
+        .child("cogni/sessions/work.json")
+        .write_str(r#"{"messages": [{"role": "user", "content": "Hi"}]}"#)
+        .unwrap();
+
+    cogni(&state)
+        .args(["session", "fork", "work", "experiment"])
+        .assert()
+        .success();
+
+    cogni(&state)
+        .args(["session", "fork", "work", "experiment"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+
+    cogni(&state)
+        .args(["session", "list"])
+        .assert()
+        .success()
+        .stdout("experiment\nwork\n");
+
+    cogni(&state)
+        .args(["session", "show", "experiment"])
+        .assert()
+        .success()
+        .stdout(">>> user\nHi\n");
+
+    cogni(&state)
+        .args(["session", "rm", "work"])
+        .assert()
+        .success();
+
+    cogni(&state)
+        .args(["session", "show", "work"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no session named \"work\""));
+
+    cogni(&state)
+        .args(["session", "list"])
+        .assert()
+        .success()
+        .stdout("experiment\n");
+}
@@ -0,0 +1,171 @@
+//! Integration tests for prompt templates
+
+use assert_cmd::Command;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use serde_json::json;
+
+fn templates() -> assert_fs::TempDir {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("commit-msg.tmpl")
+        .write_str(
+            r#"---
+model = "gpt-5-mini"
+system = "Write a commit message in {{style}} style"
+---
+>>> user
+{{input}}
+"#,
+        )
+        .unwrap();
+    dir
+}
+
+#[test]
+fn chat_with_template() {
+    let mut server = mockito::Server::new();
+    let templates = templates();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "model": "gpt-5-mini",
+            "input": [{
+                "role": "system",
+                "content": [{ "type": "input_text", "text": "Write a commit message in conventional style" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "diff --git a/README.md\n" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5-mini",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "docs: update README" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    let cmd = Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-f", "commit-msg", "--var", "style=conventional"])
+        .write_stdin("diff --git a/README.md\n")
+        .env("COGNI_TEMPLATES", templates.path())
+        .env("COGNI_CONFIG", templates.path().join("config.toml"))
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("docs: update README\n");
+}
+
+#[test]
+fn chat_template_missing_variables() {
+    let templates = templates();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-f", "commit-msg"])
+        .env("COGNI_TEMPLATES", templates.path())
+        .env("COGNI_CONFIG", templates.path().join("config.toml"))
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "missing template variables: style",
+        ));
+}
+
+#[test]
+fn chat_template_not_found() {
+    let templates = templates();
+
+    Command::cargo_bin("cogni")
+        .unwrap()
+        .args(["-f", "release-notes"])
+        .env("COGNI_TEMPLATES", templates.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "no template found for \"release-notes\"",
+        ));
+}
+
+#[test]
+fn script_with_shebang() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    let script = dir.child("haiku");
+    script
+        .write_str(
+            r#"#!/usr/bin/env cogni
+---
+model = "gpt-5-mini"
+---
+>>> system
+Reply in a haiku about the given city
+"#,
+        )
+        .unwrap();
+    std::fs::set_permissions(
+        script.path(),
+        std::os::unix::fs::PermissionsExt::from_mode(0o755),
+    )
+    .unwrap();
+
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "model": "gpt-5-mini",
+            "input": [{
+                "role": "system",
+                "content": [{ "type": "input_text", "text": "Reply in a haiku about the given city" }],
+            }, {
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "Tokyo" }],
+            }],
+        })))
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5-mini",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "Neon rain falls" }]
+             }],
+             "usage": { "input_tokens": 8, "output_tokens": 9, "total_tokens": 17 }
+        }"#,
+        )
+        .create();
+
+    // Run script directly, resolving `cogni` in shebang to the built binary
+    let bin_dir = assert_cmd::cargo::cargo_bin("cogni")
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let path = std::env::join_paths(
+        std::iter::once(bin_dir).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let cmd = Command::new(script.path())
+        .write_stdin("Tokyo")
+        .env("PATH", path)
+        .env("COGNI_CONFIG", dir.path().join("config.toml"))
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert();
+
+    mock.assert();
+
+    cmd.success().stdout("Neon rain falls\n");
+}
@@ -0,0 +1,276 @@
+//! Integration tests for token usage and cost reporting
+
+mod common;
+
+use assert_cmd::Command;
+use common::{RESPONSE, mock_response};
+use predicates::prelude::*;
+
+const CONFIG: &str = r#"
+[prices."gpt-5.5"]
+input = 1.0
+cached_input = 0.1
+output = 10.0
+"#;
+
+fn cogni(dir: &assert_fs::TempDir) -> Command {
+    std::fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    common::cogni(dir)
+}
+
+/// Mock provider rules replying with fixed usage
+fn mock_rules(dir: &assert_fs::TempDir) -> std::path::PathBuf {
+    let path = dir.path().join("rules.toml");
+    std::fs::write(
+        &path,
+        r#"
+[[rules]]
+pattern = ".*"
+reply = "OK"
+usage = { input_tokens = 1000, output_tokens = 500, cached_tokens = 200, reasoning_tokens = 100 }
+"#,
+    )
+    .unwrap();
+    path
+}
+
+#[test]
+fn chat_usage_with_details_and_cost() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_body(
+            r#"{
+             "created": 1688413145,
+             "model": "gpt-5.5",
+             "output": [{
+                 "type": "message",
+                 "role": "assistant",
+                 "content": [{ "type": "output_text", "text": "ASSISTANT REPLY" }]
+             }],
+             "usage": {
+                 "input_tokens": 1000,
+                 "input_tokens_details": { "cached_tokens": 200 },
+                 "output_tokens": 500,
+                 "output_tokens_details": { "reasoning_tokens": 100 },
+                 "total_tokens": 1500
+             }
+        }"#,
+        )
+        .create();
+
+    cogni(&dir)
+        .args(["--usage", "-u", "Hello"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert()
+        .success()
+        .stdout("ASSISTANT REPLY\n")
+        .stderr(
+            "usage: 1000 input (200 cached), 500 output (100 reasoning), 1500 total tokens, $0.005820 estimated\n",
+        );
+
+    mock.assert();
+}
+
+#[test]
+fn chat_usage_without_price() {
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    cogni(&dir)
+        .args([
+            "--provider",
+            "mock",
+            "-m",
+            "unpriced",
+            "--usage",
+            "-u",
+            "Hello there",
+        ])
+        .env_remove("COGNI_MOCK_RULES")
+        .assert()
+        .success()
+        .stdout("Hello there\n")
+        .stderr("usage: 2 input, 2 output, 4 total tokens\n");
+}
+
+#[test]
+fn batch_usage_totals() {
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    cogni(&dir)
+        .args(["batch", "--provider", "mock", "--usage"])
+        .env("COGNI_MOCK_RULES", mock_rules(&dir))
+        .write_stdin("one\ntwo\nthree\n")
+        .assert()
+        .success()
+        .stderr(
+            "total usage: 3000 input (600 cached), 1500 output (300 reasoning), 4500 total tokens, $0.017460 estimated\n",
+        );
+}
+
+#[test]
+fn session_usage_totals() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let rules = mock_rules(&dir);
+
+    cogni(&dir)
+        .args([
+            "--provider",
+            "mock",
+            "--session",
+            "work",
+            "--usage",
+            "-u",
+            "Hi",
+        ])
+        .env("COGNI_MOCK_RULES", &rules)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "session work: 1000 input (200 cached), 500 output (100 reasoning), 1500 total tokens",
+        ));
+
+    cogni(&dir)
+        .args(["--session", "work", "--usage", "-u", "Again"])
+        .env("COGNI_MOCK_RULES", &rules)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "usage: 1000 input (200 cached), 500 output (100 reasoning), 1500 total tokens, $0.005820 estimated\n",
+        ))
+        .stderr(predicate::str::contains(
+            "session work: 2000 input (400 cached), 1000 output (200 reasoning), 3000 total tokens, $0.011640 estimated\n",
+        ));
+}
+
+#[test]
+fn ledger_report() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    let mock = mock_response(&mut server).expect(3).create();
+
+    for model in ["gpt-5.5", "gpt-5.5", "unpriced"] {
+        cogni(&dir)
+            .args(["-m", model, "-u", "Hello"])
+            .env("OPENAI_API_ENDPOINT", server.url())
+            .env("OPENAI_API_KEY", "ABCDE")
+            .assert()
+            .success();
+    }
+    mock.assert();
+
+    // Mock provider replies aren't recorded
+    cogni(&dir)
+        .args(["--provider", "mock", "-u", "Hello"])
+        .assert()
+        .success();
+
+    cogni(&dir)
+        .args(["usage", "report", "--since", "7d", "--group-by", "model"])
+        .assert()
+        .success()
+        .stdout(concat!(
+            "model     requests  input  cached  output  reasoning  total  cost\n",
+            "gpt-5.5   2         2000   0       1000    0          3000   $0.0120\n",
+            "unpriced  1         1000   0       500     0          1500   -\n",
+            "total     3         3000   0       1500    0          4500   $0.0120\n",
+        ));
+
+    // Durations beyond earliest representable time include all requests
+    cogni(&dir)
+        .args(["usage", "report", "--since", "99999999w"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("total     3 "));
+
+    cogni(&dir)
+        .args(["usage", "report", "--group-by", "provider"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("openai    3 "));
+}
+
+#[test]
+fn daily_budget_refuses_requests() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    let mock = mock_response(&mut server).expect(0).create();
+
+    let ledger = dir.path().join("cogni");
+    std::fs::create_dir_all(&ledger).unwrap();
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    std::fs::write(
+        ledger.join("ledger.ndjson"),
+        format!(
+            "{{\"timestamp\":\"{timestamp}\",\"provider\":\"openai\",\"model\":\"gpt-5.5\",\"input_tokens\":1,\"output_tokens\":1,\"total_tokens\":2,\"cost\":2.5}}\n"
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("config.toml"),
+        format!("[defaults]\ndaily_budget = 2.0\n{CONFIG}"),
+    )
+    .unwrap();
+
+    common::cogni(&dir)
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .args(["-u", "Hello"])
+        .assert()
+        .code(12)
+        .stderr(predicate::str::contains(
+            "budget exceeded - spent $2.5000 today, reaching daily_budget of $2.00",
+        ));
+
+    mock.assert();
+}
+
+#[test]
+fn budget_requires_price() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    let mock = mock_response(&mut server).expect(0).create();
+
+    cogni(&dir)
+        .args(["--budget", "1", "-m", "unpriced", "-u", "Hello"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "invalid config - budget is set, but [prices] has no entry for model",
+        ));
+
+    mock.assert();
+}
+
+#[test]
+fn invocation_budget_stops_batch() {
+    let mut server = mockito::Server::new();
+    let dir = assert_fs::TempDir::new().unwrap();
+    let mock = server
+        .mock("POST", "/v1/responses")
+        .with_chunked_body(move |w| {
+            // Slow response, so requests of other records would be in flight meanwhile
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            w.write_all(RESPONSE.as_bytes())
+        })
+        .expect(1)
+        .create();
+
+    // First request costs $0.006, reaching budget, so remaining records are refused, even
+    // while sent concurrently
+    cogni(&dir)
+        .args(["batch", "-j", "4", "--budget", "0.005"])
+        .env("OPENAI_API_ENDPOINT", server.url())
+        .env("OPENAI_API_KEY", "ABCDE")
+        .write_stdin("one\ntwo\nthree\nfour\nfive\nsix\n")
+        .assert()
+        .code(12)
+        .stdout(predicate::str::contains("ASSISTANT REPLY").count(1))
+        .stderr(predicate::str::contains("reaching --budget of $0.01"));
+
+    mock.assert();
+}